@@ -0,0 +1,428 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{bail, Result};
+use clap::{builder::RangedU64ValueParser, Args, ValueEnum};
+use win_event_hook::{
+    events::{Event, NamedEvent},
+    flags::Flags,
+    handles::ModuleHandle,
+    handoff::{Overflow, ShardKey},
+    Config,
+};
+#[cfg(windows)]
+use windows::{
+    core::HSTRING,
+    Win32::System::LibraryLoader::{GetModuleHandleW, LoadLibraryW},
+};
+
+/// Arguments that select which events a hook receives.
+#[derive(Args, Debug, Clone)]
+pub struct HookArgs {
+    /// An event to hook, by name (for instance `ObjectShow`) or id (for instance `0x8002`). May be repeated.
+    #[arg(long = "event", value_name = "EVENT", value_parser = parse_event)]
+    pub events: Vec<Event>,
+
+    /// A range of event ids to hook, inclusive (for instance `0x8000..0x80FF`).
+    #[arg(long, value_name = "MIN..MAX", value_parser = parse_range, conflicts_with = "events")]
+    pub range: Option<(u32, u32)>,
+
+    /// Only hook events raised by the process with this id.
+    #[arg(long, value_name = "PID")]
+    pub pid: Option<u32>,
+
+    /// Only hook events raised by the thread with this id.
+    #[arg(long, value_name = "TID")]
+    pub tid: Option<u32>,
+
+    /// Ignore events raised by this thread.
+    #[arg(long, conflicts_with = "skip_own_process")]
+    pub skip_own_thread: bool,
+
+    /// Ignore events raised by this process.
+    #[arg(long)]
+    pub skip_own_process: bool,
+
+    /// The name of the dedicated thread that manages the hook.
+    #[arg(long, value_name = "NAME", default_value = "WinEventHookThread")]
+    pub thread_name: String,
+
+    /// Hook in context, by mapping a module into the processes raising events. Defaults to this executable.
+    ///
+    /// Note: The module must contain the hook function, so this is for advanced use cases.
+    #[arg(long, value_name = "MODULE")]
+    pub in_context: Option<Option<PathBuf>>,
+
+    /// Hand events to the handler through a queue holding up to N events, so slow output doesn't hold up the hook.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub queue: Option<usize>,
+
+    /// What happens to events that arrive when the queue is full. Defaults to block.
+    #[arg(long, value_enum, requires = "queue")]
+    pub overflow: Option<QueueOverflow>,
+
+    /// Hand events to the handler from N worker threads, in parallel. Each worker has its own queue.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<u32>::new().range(1..)
+    )]
+    pub workers: Option<u32>,
+
+    /// Which events stay in order, by being handled by the same worker. Defaults to window.
+    #[arg(long, value_enum, requires = "workers")]
+    pub shard_key: Option<WorkerShardKey>,
+}
+
+/// Which events stay in order across workers, see [`ShardKey`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerShardKey {
+    /// Events for the same window.
+    Window,
+    /// Events raised by the same thread.
+    Thread,
+    /// Events of the same type.
+    Event,
+}
+
+impl From<WorkerShardKey> for ShardKey {
+    fn from(key: WorkerShardKey) -> Self {
+        match key {
+            WorkerShardKey::Window => ShardKey::Window,
+            WorkerShardKey::Thread => ShardKey::Thread,
+            WorkerShardKey::Event => ShardKey::Event,
+        }
+    }
+}
+
+/// What happens to events that arrive when the queue is full, see [`Overflow`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOverflow {
+    /// Wait for space in the queue, holding up the hook.
+    Block,
+    /// Drop the arriving event.
+    DropNewest,
+    /// Drop the oldest queued event.
+    DropOldest,
+    /// Replace a queued event for the same window, object and child, or else drop the arriving event.
+    Coalesce,
+}
+
+impl From<QueueOverflow> for Overflow {
+    fn from(overflow: QueueOverflow) -> Self {
+        match overflow {
+            QueueOverflow::Block => Overflow::Block,
+            QueueOverflow::DropNewest => Overflow::DropNewest,
+            QueueOverflow::DropOldest => Overflow::DropOldest,
+            QueueOverflow::Coalesce => Overflow::Coalesce,
+        }
+    }
+}
+
+impl HookArgs {
+    /// Creates a hook [`Config`] from the arguments, hooking all events if none are selected.
+    pub fn config(&self) -> Result<Config> {
+        // the default flags skip our own process, so they're replaced with exactly what was asked for
+        let mut builder = Config::builder()
+            .with_dedicated_thread_name(&self.thread_name)
+            .with_flags(Flags::OUT_OF_CONTEXT);
+
+        if !self.events.is_empty() {
+            builder = builder.with_events(self.events.clone());
+        }
+
+        if let Some((min, max)) = self.range {
+            builder = builder.with_event_range(min, max);
+        }
+
+        if let Some(pid) = self.pid {
+            builder = builder.with_process_id(pid);
+        }
+
+        if let Some(tid) = self.tid {
+            builder = builder.with_thread_id(tid);
+        }
+
+        if self.skip_own_process {
+            builder = builder.skip_own_process();
+        }
+
+        if self.skip_own_thread {
+            builder = builder.skip_own_thread();
+        }
+
+        if let Some(module) = &self.in_context {
+            builder = builder.with_module_context(load_module(module)?);
+        }
+
+        if let Some(capacity) = self.queue {
+            builder = builder.with_handoff(
+                capacity,
+                self.overflow.map_or(Overflow::Block, Overflow::from),
+            );
+        }
+
+        if let Some(workers) = self.workers {
+            builder = builder.with_sharding(
+                workers,
+                self.shard_key.map(ShardKey::from).unwrap_or_default(),
+            );
+        }
+
+        let config = builder.finish();
+
+        validate(&config)?;
+
+        Ok(config)
+    }
+}
+
+/// Explains why a [`Config`] is invalid, as [`Config::is_valid`] only reports that it is.
+fn validate(config: &Config) -> Result<()> {
+    if config.event_min < Event::MIN || config.event_max > Event::MAX {
+        bail!(
+            "Events {:#X}..{:#X} are outside of the hookable range {:#X}..{:#X}",
+            config.event_min,
+            config.event_max,
+            Event::MIN,
+            Event::MAX
+        );
+    }
+
+    if !config.dw_flags.is_valid() {
+        bail!(
+            "Flags {:?} are not a valid combination; at most one of --skip-own-thread and --skip-own-process may be used",
+            config.dw_flags
+        );
+    }
+
+    if config.dw_flags.contains(Flags::IN_CONTEXT) != config.module_handle.is_some() {
+        bail!("In context hooks require a module, and out of context hooks must not have one");
+    }
+
+    if !config.is_valid() {
+        bail!("The selected events and flags do not form a valid hook config");
+    }
+
+    Ok(())
+}
+
+/// Loads the module containing the hook function, for in context hooks. Without a path, this is the executable.
+#[cfg(windows)]
+fn load_module(module: &Option<PathBuf>) -> Result<ModuleHandle> {
+    let handle = match module {
+        Some(module) => unsafe { LoadLibraryW(&HSTRING::from(module.as_path())) },
+        None => unsafe { GetModuleHandleW(None) },
+    };
+
+    match handle {
+        Ok(handle) => Ok(ModuleHandle::from_raw(handle.0 as usize)),
+        Err(err) => bail!(
+            "Unable to load module '{}': {err}",
+            module
+                .as_deref()
+                .unwrap_or("this executable".as_ref())
+                .display()
+        ),
+    }
+}
+
+/// Loads the module containing the hook function, which isn't possible on this platform.
+#[cfg(not(windows))]
+fn load_module(_module: &Option<PathBuf>) -> Result<ModuleHandle> {
+    bail!("In context hooks are only supported on windows")
+}
+
+/// Parses an [`Event`] by [`NamedEvent`] name (ignoring case), or by decimal or hex id.
+pub fn parse_event(value: &str) -> Result<Event, String> {
+    if let Some(named) = NamedEvent::ALL
+        .iter()
+        .find(|named| named.name().eq_ignore_ascii_case(value))
+    {
+        return Ok(Event::Named(*named));
+    }
+
+    parse_id(value)
+        .map(Event::from)
+        .map_err(|_| format!("'{value}' is not a known event name or id"))
+}
+
+/// Parses an inclusive range of event ids, as `MIN..MAX`.
+pub fn parse_range(value: &str) -> Result<(u32, u32), String> {
+    let (min, max) = value
+        .split_once("..")
+        .ok_or_else(|| format!("'{value}' is not a range, expected MIN..MAX"))?;
+
+    let min = parse_id(min.trim()).map_err(|err| format!("invalid range minimum: {err}"))?;
+    let max = parse_id(max.trim()).map_err(|err| format!("invalid range maximum: {err}"))?;
+
+    if min > max {
+        return Err(format!("range minimum {min:#X} is above maximum {max:#X}"));
+    }
+
+    Ok((min, max))
+}
+
+/// Parses a decimal, or `0x` prefixed hex, id.
+pub fn parse_id(value: &str) -> Result<u32, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    parsed.map_err(|_| format!("'{value}' is not a decimal or hex id"))
+}
+
+/// Parses a duration, such as `500ms`, `30s`, `5m` or `1h`. Plain numbers are seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' is not a duration, for instance 30s"))?;
+
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        unit => {
+            return Err(format!(
+                "unknown duration unit '{unit}', expected ms, s, m or h"
+            ))
+        }
+    };
+
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|err| format!("'{value}' is not a duration: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::Parser;
+    use win_event_hook::{
+        events::{Event, NamedEvent},
+        flags::Flags,
+        handoff::{Handoff, Overflow, ShardKey, Sharding},
+    };
+
+    use super::{parse_duration, parse_event, parse_range, HookArgs};
+
+    #[derive(Parser, Debug)]
+    struct TestArgs {
+        #[command(flatten)]
+        hook: HookArgs,
+    }
+
+    fn hook_args(args: &[&str]) -> Result<HookArgs, clap::Error> {
+        TestArgs::try_parse_from(std::iter::once("test").chain(args.iter().copied()))
+            .map(|args| args.hook)
+    }
+
+    #[test]
+    fn maps_flags_onto_config() {
+        let config = hook_args(&[
+            "--event",
+            "ObjectShow",
+            "--event",
+            "objecthide",
+            "--pid",
+            "42",
+            "--skip-own-thread",
+            "--thread-name",
+            "Watcher",
+        ])
+        .unwrap()
+        .config()
+        .unwrap();
+
+        assert_eq!(
+            config.event_filter,
+            Some(vec![
+                Event::Named(NamedEvent::ObjectShow),
+                Event::Named(NamedEvent::ObjectHide)
+            ])
+        );
+        assert_eq!(config.id_process, 42);
+        assert_eq!(
+            config.dw_flags,
+            Flags::OUT_OF_CONTEXT | Flags::SKIP_OWN_THREAD
+        );
+        assert_eq!(config.dedicated_thread_name.as_deref(), Some("Watcher"));
+        assert_eq!(config.handoff, None);
+
+        let config = hook_args(&["--queue", "64", "--overflow", "drop-oldest"])
+            .unwrap()
+            .config()
+            .unwrap();
+        assert_eq!(
+            config.handoff,
+            Some(Handoff {
+                capacity: 64,
+                overflow: Overflow::DropOldest
+            })
+        );
+        assert_eq!(config.sharding, None);
+
+        let config = hook_args(&["--workers", "4", "--shard-key", "thread"])
+            .unwrap()
+            .config()
+            .unwrap();
+        assert_eq!(
+            config.sharding,
+            Some(Sharding {
+                workers: 4,
+                key: ShardKey::Thread
+            })
+        );
+
+        assert!(hook_args(&["--skip-own-thread", "--skip-own-process"]).is_err());
+        assert!(hook_args(&["--event", "ObjectShow", "--range", "1..2"]).is_err());
+        assert!(hook_args(&["--overflow", "coalesce"]).is_err());
+        assert!(hook_args(&["--queue", "0"]).is_err());
+        assert!(hook_args(&["--shard-key", "window"]).is_err());
+        assert!(hook_args(&["--range", "0..0x10"])
+            .unwrap()
+            .config()
+            .unwrap_err()
+            .to_string()
+            .contains("outside of the hookable range"));
+    }
+
+    #[test]
+    fn parses_events_and_ranges() {
+        assert_eq!(
+            parse_event("objectshow"),
+            Ok(Event::Named(NamedEvent::ObjectShow))
+        );
+        assert_eq!(
+            parse_event("0x8002"),
+            Ok(Event::Named(NamedEvent::ObjectShow))
+        );
+        assert!(parse_event("NotAnEvent").is_err());
+
+        assert_eq!(parse_range("0x8000..0x80FF"), Ok((0x8000, 0x80FF)));
+        assert!(parse_range("0x80FF..0x8000").is_err());
+        assert!(parse_range("0x8000").is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert!(parse_duration("30 days").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+use clap::{Args, Subcommand, ValueEnum};
+use serde::Serialize;
+use win_event_hook::events::{Event, EventCategory, NamedEvent};
+
+use crate::args::parse_id;
+
+/// Lists the events that can be hooked, or decodes an event id.
+#[derive(Args, Debug)]
+pub struct EventsArgs {
+    #[command(subcommand)]
+    pub command: Option<EventsCommand>,
+
+    /// Only lists events in this category (named, aia, oem, uia, uia_property or unknown).
+    #[arg(long, value_parser = parse_category)]
+    pub category: Option<EventCategory>,
+
+    /// How events are printed.
+    #[arg(id = "catalog_format", long = "format", value_enum, default_value_t = CatalogFormat::Table, global = true)]
+    pub format: CatalogFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EventsCommand {
+    /// Decodes an event id (for instance `0x800C`), as it would be classified by the library.
+    Decode {
+        /// The decimal, or `0x` prefixed hex, event id.
+        #[arg(value_parser = parse_id)]
+        id: u32,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    /// An aligned, human readable table.
+    Table,
+    /// A single JSON document.
+    Json,
+}
+
+/// A single [`NamedEvent`] in the catalog.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct NamedEntry {
+    name: &'static str,
+    sdk_name: &'static str,
+    id: u32,
+    id_hex: String,
+    description: &'static str,
+}
+
+/// A range of event ids, for a category of events.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct RangeEntry {
+    category: &'static str,
+    /// The first id within the range.
+    first: u32,
+    /// The last id within the range (inclusive).
+    last: u32,
+    description: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct Catalog {
+    events: Vec<NamedEntry>,
+    ranges: Vec<RangeEntry>,
+}
+
+/// The classification of a single event id.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct Decoded {
+    id: u32,
+    id_hex: String,
+    category: &'static str,
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sdk_name: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<RangeEntry>,
+    description: &'static str,
+}
+
+pub fn run(args: EventsArgs) -> Result<()> {
+    match args.command {
+        Some(EventsCommand::Decode { id }) => {
+            let decoded = decode(id);
+
+            match args.format {
+                CatalogFormat::Table => print!("{}", decoded_table(&decoded)),
+                CatalogFormat::Json => println!("{}", serde_json::to_string_pretty(&decoded)?),
+            }
+        }
+        None => {
+            let catalog = catalog(args.category);
+
+            match args.format {
+                CatalogFormat::Table => print!("{}", catalog_table(&catalog)),
+                CatalogFormat::Json => println!("{}", serde_json::to_string_pretty(&catalog)?),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_category(value: &str) -> Result<EventCategory, String> {
+    value.parse().map_err(|err| format!("{err}"))
+}
+
+fn named_entry(named: NamedEvent) -> NamedEntry {
+    let id = u32::from(named);
+
+    NamedEntry {
+        name: named.name(),
+        sdk_name: named.sdk_name(),
+        id,
+        id_hex: format!("{id:#06X}"),
+        description: named.description(),
+    }
+}
+
+fn range_entry(category: EventCategory) -> Option<RangeEntry> {
+    category.range().map(|range| RangeEntry {
+        category: category.name(),
+        first: range.start,
+        last: range.end - 1,
+        description: category.description(),
+    })
+}
+
+/// Builds the catalog of events, optionally limited to a single category.
+fn catalog(category: Option<EventCategory>) -> Catalog {
+    let included = |candidate: EventCategory| category.is_none_or(|category| category == candidate);
+
+    let mut events = match included(EventCategory::Named) {
+        true => NamedEvent::ALL.iter().copied().map(named_entry).collect(),
+        false => Vec::new(),
+    };
+    events.sort_by_key(|entry| entry.id);
+
+    let ranges = EventCategory::ALL
+        .into_iter()
+        .filter(|candidate| included(*candidate))
+        .filter_map(range_entry)
+        .collect();
+
+    Catalog { events, ranges }
+}
+
+/// Classifies an event id, using `Event::from(u32)` just as the library does as events are dispatched.
+fn decode(id: u32) -> Decoded {
+    let event = Event::from(id);
+    let category = event.category();
+
+    let (sdk_name, description) = match event {
+        Event::Named(named) => (Some(named.sdk_name()), named.description()),
+        _ => (None, category.description()),
+    };
+
+    Decoded {
+        id,
+        id_hex: format!("{id:#06X}"),
+        category: category.name(),
+        event: event.to_string(),
+        sdk_name,
+        range: range_entry(category),
+        description,
+    }
+}
+
+/// The first sentence of a description, for display in a table.
+fn summary(description: &str) -> &str {
+    match description.find(". ") {
+        Some(end) => &description[..=end],
+        None => description,
+    }
+}
+
+fn catalog_table(catalog: &Catalog) -> String {
+    let mut out = String::new();
+
+    if !catalog.events.is_empty() {
+        let name_width = catalog
+            .events
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
+        let sdk_width = catalog
+            .events
+            .iter()
+            .map(|entry| entry.sdk_name.len())
+            .max()
+            .unwrap_or(0);
+
+        out += &format!(
+            "{:<name_width$}  {:<sdk_width$}  {:<6}  DESCRIPTION\n",
+            "NAME", "SDK NAME", "ID"
+        );
+
+        for entry in &catalog.events {
+            out += &format!(
+                "{:<name_width$}  {:<sdk_width$}  {:<6}  {}\n",
+                entry.name,
+                entry.sdk_name,
+                entry.id_hex,
+                summary(entry.description)
+            );
+        }
+    }
+
+    if !catalog.ranges.is_empty() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        out += &format!("{:<12}  {:<15}  DESCRIPTION\n", "CATEGORY", "IDS");
+
+        for range in &catalog.ranges {
+            out += &format!(
+                "{:<12}  {:<15}  {}\n",
+                range.category,
+                format!("{:#06X}..={:#06X}", range.first, range.last),
+                range.description
+            );
+        }
+    }
+
+    out
+}
+
+fn decoded_table(decoded: &Decoded) -> String {
+    let mut out = format!(
+        "id:          {} ({})\ncategory:    {}\nevent:       {}\n",
+        decoded.id_hex, decoded.id, decoded.category, decoded.event
+    );
+
+    if let Some(sdk_name) = decoded.sdk_name {
+        out += &format!("sdk name:    {sdk_name}\n");
+    }
+
+    if let Some(range) = &decoded.range {
+        out += &format!("range:       {:#06X}..={:#06X}\n", range.first, range.last);
+    }
+
+    out += &format!("description: {}\n", decoded.description);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use win_event_hook::events::{Event, EventCategory, NamedEvent};
+
+    use super::{catalog, decode};
+
+    #[test]
+    fn decode_matches_library_classification() {
+        let mut ids = vec![0, 1, 0x800C, 0xFFFF_FFFF];
+        ids.extend(NamedEvent::ALL.iter().map(|named| u32::from(*named)));
+        for category in EventCategory::ALL {
+            if let Some(range) = category.range() {
+                ids.extend([range.start - 1, range.start, range.end - 1, range.end]);
+            }
+        }
+
+        for id in ids {
+            let decoded = decode(id);
+            let event = Event::from(id);
+
+            assert_eq!(decoded.category, event.category().name(), "{id:#X}");
+            assert_eq!(decoded.event, event.to_string(), "{id:#X}");
+        }
+
+        let decoded = decode(0x800C);
+        assert_eq!(decoded.event, "ObjectNameChange");
+        assert_eq!(decoded.sdk_name, Some("EVENT_OBJECT_NAMECHANGE"));
+    }
+
+    #[test]
+    fn catalog_filters_by_category() {
+        let all = catalog(None);
+        assert_eq!(all.events.len(), NamedEvent::ALL.len());
+        assert_eq!(all.ranges.len(), 4);
+        assert!(all.events.windows(2).all(|pair| pair[0].id < pair[1].id));
+
+        let uia = catalog(Some(EventCategory::Uia));
+        assert!(uia.events.is_empty());
+        assert_eq!(uia.ranges.len(), 1);
+        assert_eq!(uia.ranges[0].category, "uia");
+
+        let named = catalog(Some(EventCategory::Named));
+        assert_eq!(named.events.len(), NamedEvent::ALL.len());
+        assert!(named.ranges.is_empty());
+    }
+}
@@ -0,0 +1,415 @@
+//! Filter expressions, which select events by their fields.
+//!
+//! An expression is either an event name (or id), or a comparison of a field to a value, combined
+//! with `and`, `or`, `not` and parentheses. For instance:
+//!
+//! ```text
+//! ObjectShow and object==window
+//! (SystemForeground or SystemMinimizeEnd) and thread!=4120
+//! category==uia or id>=0x8000
+//! ```
+//!
+//! | Field      | Values                                                                 |
+//! |------------|------------------------------------------------------------------------|
+//! | `event`    | an event name or id, compared with `==` or `!=`                        |
+//! | `category` | `named`, `aia`, `oem`, `uia`, `uia_property` or `unknown`              |
+//! | `id`       | the raw event id                                                       |
+//! | `hwnd`     | the window handle                                                      |
+//! | `object`   | an object id, or its name (for instance `window` or `OBJID_CLIENT`)    |
+//! | `child`    | a child id, or `self`                                                  |
+//! | `thread`   | the id of the thread that raised the event                             |
+//! | `time`     | the time (in milliseconds, since system start) the event was raised    |
+
+use std::{fmt, str::FromStr};
+
+use win_event_hook::{
+    events::{Event, EventCategory},
+    objects::{ObjectId, CHILD_ID_SELF},
+    record::EventRecord,
+};
+
+use crate::args::{parse_event, parse_id};
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    /// Determines if a record is selected by the filter.
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        self.expr.matches(record)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected '{token}' in filter '{source}'"));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Event(Event),
+    Category(EventCategory),
+    Number(Field, Op, i64),
+}
+
+impl Expr {
+    fn matches(&self, record: &EventRecord) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(record) && b.matches(record),
+            Expr::Or(a, b) => a.matches(record) || b.matches(record),
+            Expr::Not(a) => !a.matches(record),
+            Expr::Event(event) => record.event == *event,
+            Expr::Category(category) => record.event.category() == *category,
+            Expr::Number(field, op, value) => op.compare(field.value(record), *value),
+        }
+    }
+}
+
+/// A numeric field of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    Hwnd,
+    Object,
+    Child,
+    Thread,
+    Time,
+}
+
+impl Field {
+    fn value(self, record: &EventRecord) -> i64 {
+        match self {
+            Field::Id => u32::from(record.event).into(),
+            Field::Hwnd => record.hwnd.to_raw() as i64,
+            Field::Object => record.id_object.into(),
+            Field::Child => record.id_child.into(),
+            Field::Thread => record.id_event_thread.into(),
+            Field::Time => record.event_time.into(),
+        }
+    }
+
+    /// Parses a value of the field, resolving names where the field has them.
+    fn parse(self, value: &str) -> Result<i64, String> {
+        let name = value.to_ascii_uppercase();
+
+        match self {
+            Field::Object => {
+                let name = name.strip_prefix("OBJID_").unwrap_or(&name);
+
+                if let Some(object) = ObjectId::ALL.iter().find(|object| {
+                    object.name().and_then(|n| n.strip_prefix("OBJID_")) == Some(name)
+                }) {
+                    return Ok(i32::from(*object).into());
+                }
+            }
+            Field::Child if name == "SELF" || name == "CHILDID_SELF" => {
+                return Ok(CHILD_ID_SELF.into());
+            }
+            _ => {}
+        }
+
+        match value.strip_prefix('-') {
+            Some(negative) => parse_id(negative).map(|value| -i64::from(value)),
+            None => parse_id(value).map(i64::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(self, a: T, b: T) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => f.write_str(word),
+            Token::Op(op) => f.write_str(match op {
+                Op::Eq => "==",
+                Op::Ne => "!=",
+                Op::Lt => "<",
+                Op::Le => "<=",
+                Op::Gt => ">",
+                Op::Ge => ">=",
+            }),
+            Token::And => f.write_str("and"),
+            Token::Or => f.write_str("or"),
+            Token::Not => f.write_str("not"),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let next_is = |chars: &mut std::iter::Peekable<std::str::CharIndices>, expected| {
+            chars.next_if(|(_, c)| *c == expected).is_some()
+        };
+
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' if next_is(&mut chars, '=') => Token::Op(Op::Eq),
+            '!' if next_is(&mut chars, '=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is(&mut chars, '=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '&' if next_is(&mut chars, '&') => Token::And,
+            '|' if next_is(&mut chars, '|') => Token::Or,
+            c if c.is_alphanumeric() || c == '_' || c == '-' => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                {
+                    end = index + c.len_utf8();
+                }
+
+                let word = &source[start..end];
+                match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word(word.to_string()),
+                }
+            }
+            c => return Err(format!("unexpected '{c}' in filter '{source}'")),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, where `not` binds tighter than `and`, which binds tighter than `or`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.next();
+
+                Ok(Expr::Not(Box::new(self.not()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("expected ')'".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => match self.peek() {
+                Some(Token::Op(op)) => {
+                    let op = *op;
+                    self.next();
+
+                    match self.next() {
+                        Some(Token::Word(value)) => comparison(&word, op, &value),
+                        _ => Err(format!("expected a value after '{word}{}'", Token::Op(op))),
+                    }
+                }
+                _ => parse_event(&word).map(Expr::Event),
+            },
+            Some(token) => Err(format!("unexpected '{token}'")),
+            None => Err("expected an event or comparison".to_string()),
+        }
+    }
+}
+
+fn comparison(name: &str, op: Op, value: &str) -> Result<Expr, String> {
+    let equality = |expr: Expr| match op {
+        Op::Eq => Ok(expr),
+        Op::Ne => Ok(Expr::Not(Box::new(expr))),
+        _ => Err(format!("'{name}' can only be compared with == or !=")),
+    };
+
+    let field = match name.to_ascii_lowercase().as_str() {
+        "event" => return equality(Expr::Event(parse_event(value)?)),
+        "category" => {
+            let category = value.parse().map_err(|err| format!("{err}"))?;
+
+            return equality(Expr::Category(category));
+        }
+        "id" => Field::Id,
+        "hwnd" => Field::Hwnd,
+        "object" => Field::Object,
+        "child" => Field::Child,
+        "thread" => Field::Thread,
+        "time" => Field::Time,
+        _ => return Err(format!("unknown field '{name}'")),
+    };
+
+    let value = field
+        .parse(value)
+        .map_err(|_| format!("'{value}' is not a valid value for '{name}'"))?;
+
+    Ok(Expr::Number(field, op, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use win_event_hook::{events::NamedEvent, record::EventRecord};
+
+    use super::Filter;
+    use crate::fixtures::record;
+
+    #[test]
+    fn matches_expressions() {
+        let show_window = record(NamedEvent::ObjectShow);
+        let show_cursor = EventRecord {
+            id_object: -9,
+            ..record(NamedEvent::ObjectShow)
+        };
+        let hide_window = EventRecord {
+            id_event_thread: 77,
+            ..record(NamedEvent::ObjectHide)
+        };
+
+        let filter: Filter = "ObjectShow and object==window".parse().unwrap();
+        assert!(filter.matches(&show_window));
+        assert!(!filter.matches(&show_cursor));
+        assert!(!filter.matches(&hide_window));
+
+        let filter: Filter = "not (objectshow || thread<100) and child==self"
+            .parse()
+            .unwrap();
+        assert!(!filter.matches(&show_window));
+        assert!(!filter.matches(&hide_window));
+
+        let filter: Filter = "event!=ObjectShow and object==OBJID_WINDOW and hwnd==0x30F24"
+            .parse()
+            .unwrap();
+        assert!(filter.matches(&hide_window));
+
+        let filter: Filter = "category==named and id>=0x8002 and object>-10"
+            .parse()
+            .unwrap();
+        assert!(filter.matches(&show_cursor));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for source in [
+            "",
+            "NotAnEvent",
+            "ObjectShow and",
+            "(ObjectShow",
+            "object==nowhere",
+            "colour==red",
+            "event<ObjectShow",
+            "ObjectShow ObjectHide",
+        ] {
+            assert!(source.parse::<Filter>().is_err(), "{source}");
+        }
+    }
+}
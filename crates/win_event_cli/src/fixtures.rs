@@ -0,0 +1,22 @@
+//! Event records shared by the cli's tests.
+
+use win_event_hook::{
+    events::{Event, NamedEvent},
+    handles::WindowHandle,
+    record::EventRecord,
+};
+
+/// Returns an [`EventRecord`] for a given event, raised by window `0x00030F24` itself on thread `4120`.
+///
+/// Note: Vary the other fields with struct update syntax, for instance
+/// `EventRecord { id_event_thread: 77, ..record(event) }`.
+pub fn record(event: NamedEvent) -> EventRecord {
+    EventRecord::new(
+        Event::Named(event),
+        WindowHandle::from_raw(0x30F24),
+        0,
+        0,
+        4120,
+        48213050,
+    )
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use tracing::warn;
+use win_event_hook::{
+    capture::{CaptureHeader, CaptureReader, MAGIC},
+    errors::Error,
+    jsonl::JsonlReader,
+    record::EventRecord,
+};
+
+/// Recorded events, read from either a capture or JSON Lines file.
+pub enum Input {
+    Capture(Box<CaptureReader<BufReader<File>>>),
+    Jsonl(JsonlReader<BufReader<File>>),
+}
+
+impl Input {
+    /// Opens a file of recorded events, detecting its format from its content.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Unable to open '{}'", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let is_capture = reader.fill_buf()?.starts_with(&MAGIC);
+
+        Ok(match is_capture {
+            true => {
+                Input::Capture(Box::new(CaptureReader::new(reader).with_context(|| {
+                    format!("Unable to read capture '{}'", path.display())
+                })?))
+            }
+            false => Input::Jsonl(JsonlReader::new(reader)),
+        })
+    }
+
+    /// The capture header, if the events were read from a capture.
+    pub fn header(&self) -> Option<&CaptureHeader> {
+        match self {
+            Input::Capture(reader) => Some(reader.header()),
+            Input::Jsonl(_) => None,
+        }
+    }
+}
+
+impl Iterator for Input {
+    type Item = EventRecord;
+
+    /// Obtains the next record, logging (and skipping) those that can't be read.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            // captures resync past invalid records themselves, so errors are io failures
+            Input::Capture(reader) => match reader.next()? {
+                Ok(record) => Some(record),
+                Err(err) => {
+                    warn!(?err, "failed to read capture");
+
+                    None
+                }
+            },
+            Input::Jsonl(reader) => loop {
+                match reader.next()? {
+                    Ok(record) => return Some(record),
+                    Err(Error::Io(err)) => {
+                        warn!(?err, "failed to read records");
+
+                        return None;
+                    }
+                    Err(err) => warn!(%err, "skipping invalid record"),
+                }
+            },
+        }
+    }
+}
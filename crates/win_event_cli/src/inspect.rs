@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::Args;
+use win_event_hook::{
+    capture::CaptureHeader, events::Event, record::EventRecord, sequence::GapDetector,
+    time::format_rfc3339,
+};
+
+use crate::input::Input;
+
+/// The width, in characters, of the largest histogram bar.
+const BAR_WIDTH: usize = 40;
+
+/// The number of buckets in the events over time histogram.
+const TIMELINE_BUCKETS: usize = 10;
+
+/// Summarizes recorded events: header metadata, time span, event histograms and the most active windows and threads.
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// The capture, or JSON Lines, file to inspect.
+    #[arg(value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// The number of windows and threads to list.
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+pub fn run(args: InspectArgs) -> Result<()> {
+    let mut input = Input::open(&args.input)?;
+    let mut inspection = Inspection::default();
+
+    for record in input.by_ref() {
+        inspection.record(&record);
+    }
+
+    let mut out = String::new();
+
+    writeln!(out, "file:      {}", args.input.display())?;
+    match &input {
+        Input::Capture(reader) => {
+            write_header(&mut out, reader.header())?;
+            writeln!(
+                out,
+                "integrity: {} bytes skipped, {} resyncs, {}",
+                reader.skipped_bytes(),
+                reader.resyncs(),
+                match reader.is_truncated() {
+                    true => "truncated",
+                    false => "complete",
+                }
+            )?;
+        }
+        Input::Jsonl(_) => writeln!(out, "format:    json lines")?,
+    }
+
+    inspection.write(&mut out, args.top)?;
+
+    print!("{out}");
+
+    Ok(())
+}
+
+fn write_header(out: &mut String, header: &CaptureHeader) -> fmt::Result {
+    let config = &header.config;
+
+    writeln!(out, "format:    capture, version {}", header.version)?;
+    writeln!(out, "started:   {}", format_rfc3339(header.start_time))?;
+    writeln!(
+        out,
+        "host:      {} ({} {}, win_event_hook {})",
+        header.host.hostname, header.host.os, header.host.arch, header.host.library_version
+    )?;
+    writeln!(
+        out,
+        "events:    {:#06X}..{:#06X}{}",
+        config.event_min,
+        config.event_max,
+        match &config.event_filter {
+            Some(event_filter) => format!(
+                ", filtered to {}",
+                event_filter
+                    .iter()
+                    .map(Event::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            None => String::new(),
+        }
+    )?;
+    writeln!(
+        out,
+        "scope:     process {}, thread {}, flags {:?}",
+        config.id_process, config.id_thread, config.dw_flags
+    )
+}
+
+/// Aggregated statistics of a set of records.
+#[derive(Debug)]
+pub struct Inspection {
+    records: u64,
+    first_event_time: Option<u32>,
+    last_event_time: u32,
+    /// The time between the first record, and the latest record seen.
+    elapsed: Duration,
+    /// The number of records in each second since the first record.
+    timeline: Vec<u64>,
+    events: HashMap<Event, u64>,
+    windows: HashMap<usize, u64>,
+    threads: HashMap<u32, u64>,
+    /// Gaps in the sequence numbers of records, which indicate records were lost.
+    sequence: GapDetector,
+    /// Whether any record had a sequence number.
+    sequenced: bool,
+}
+
+impl Default for Inspection {
+    fn default() -> Self {
+        Self {
+            records: 0,
+            first_event_time: None,
+            last_event_time: 0,
+            elapsed: Duration::ZERO,
+            timeline: Vec::new(),
+            events: HashMap::new(),
+            windows: HashMap::new(),
+            threads: HashMap::new(),
+            // records are complete, so sharded hooks' sequence numbers may arrive any distance out of order
+            sequence: GapDetector::starting_at(0).with_reorder_window(u64::MAX),
+            sequenced: false,
+        }
+    }
+}
+
+impl Inspection {
+    /// Adds a record to the statistics.
+    pub fn record(&mut self, record: &EventRecord) {
+        let first_event_time = *self.first_event_time.get_or_insert(record.event_time);
+
+        // event times wrap every ~49.7 days, and may be slightly out of order
+        let delta = record.event_time.wrapping_sub(self.last_event_time);
+        if self.records == 0 || delta <= i32::MAX as u32 {
+            self.elapsed += Duration::from_millis(match self.records {
+                0 => 0,
+                _ => delta as u64,
+            });
+            self.last_event_time = record.event_time;
+        }
+
+        let second = record.event_time.wrapping_sub(first_event_time) / 1000;
+        let second = (second as usize).min(self.elapsed.as_secs() as usize);
+        if self.timeline.len() <= second {
+            self.timeline.resize(second + 1, 0);
+        }
+        self.timeline[second] += 1;
+
+        self.records += 1;
+        *self.events.entry(record.event).or_default() += 1;
+        *self.windows.entry(record.hwnd.to_raw()).or_default() += 1;
+        *self.threads.entry(record.id_event_thread).or_default() += 1;
+        self.sequence.observe_record(record);
+        self.sequenced |= record.sequence.is_some();
+    }
+
+    /// Writes a human readable summary of the statistics.
+    pub fn write(&self, out: &mut String, top: usize) -> fmt::Result {
+        writeln!(out, "records:   {}", self.records)?;
+
+        if let Some(first_event_time) = self.first_event_time {
+            writeln!(
+                out,
+                "span:      {:.3}s (event times {}..{})",
+                self.elapsed.as_secs_f64(),
+                first_event_time,
+                self.last_event_time
+            )?;
+        }
+
+        if self.sequenced {
+            // every record has been seen, so the sequence numbers not yet observed are missing
+            let mut sequence = self.sequence.clone();
+            sequence.finish();
+
+            writeln!(
+                out,
+                "sequence:  {} missing in {} gaps, {} out of order",
+                sequence.missing(),
+                sequence.gaps(),
+                sequence.out_of_order()
+            )?;
+        }
+
+        writeln!(out, "\nevents")?;
+        let events = top_counts(
+            self.events
+                .iter()
+                .map(|(event, count)| (event.to_string(), *count)),
+            usize::MAX,
+        );
+        write_histogram(out, &events)?;
+
+        writeln!(out, "\nevents over time")?;
+        let bucket_secs = self.timeline.len().div_ceil(TIMELINE_BUCKETS).max(1);
+        let timeline = self
+            .timeline
+            .chunks(bucket_secs)
+            .enumerate()
+            .map(|(index, chunk)| {
+                (
+                    format!("+{}s", index * bucket_secs),
+                    chunk.iter().sum::<u64>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        write_histogram(out, &timeline)?;
+
+        writeln!(out, "\ntop windows")?;
+        let windows = top_counts(self.windows.iter().map(|(k, v)| (*k, *v)), top)
+            .into_iter()
+            .map(|(hwnd, count)| (format!("0x{hwnd:08X}"), count))
+            .collect::<Vec<_>>();
+        write_histogram(out, &windows)?;
+
+        writeln!(out, "\ntop threads")?;
+        let threads = top_counts(self.threads.iter().map(|(k, v)| (*k, *v)), top)
+            .into_iter()
+            .map(|(thread, count)| (thread.to_string(), count))
+            .collect::<Vec<_>>();
+        write_histogram(out, &threads)
+    }
+}
+
+/// The `n` highest counts, in descending order.
+fn top_counts<K: Ord>(counts: impl Iterator<Item = (K, u64)>, n: usize) -> Vec<(K, u64)> {
+    let mut counts = counts.collect::<Vec<_>>();
+
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(n);
+
+    counts
+}
+
+fn write_histogram(out: &mut String, rows: &[(String, u64)]) -> fmt::Result {
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let max = rows
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (label, count) in rows {
+        let bar = (*count as usize * BAR_WIDTH).div_ceil(max as usize);
+
+        writeln!(
+            out,
+            "  {label:<label_width$}  {count:>8}  {}",
+            "#".repeat(bar)
+        )?;
+    }
+
+    Ok(())
+}
@@ -1,55 +1,194 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    io::{self, Write},
+    net::SocketAddr,
+    process::ExitCode,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::Result;
+use args::HookArgs;
+use clap::{Parser, Subcommand};
+use metrics::{HookMetrics, MetricsServer, ProcessCounts};
+use output::{Formatter, OutputArgs};
+use stop::{Stop, StopArgs, Stopper};
+use tracing::info;
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
-use win_event_hook::events::{Event, NamedEvent};
+use win_event_hook::{
+    diagnostics::diagnostics, handles::WindowHandle, record::EventRecord, Config,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+mod args;
+mod events;
+mod filter;
+#[cfg(test)]
+mod fixtures;
+mod input;
+mod inspect;
+mod merge;
+mod metrics;
+mod on;
+mod output;
+mod record;
+mod replay;
+mod serve;
+mod stop;
+mod top;
+
+/// Prints windows accessibility events as they occur.
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Serves Prometheus metrics at the given address (for example, `127.0.0.1:9184`).
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+
+    #[command(flatten)]
+    hook: HookArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    stop: StopArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Record(record::RecordArgs),
+    Replay(replay::ReplayArgs),
+    Inspect(inspect::InspectArgs),
+    Merge(merge::MergeArgs),
+    Events(events::EventsArgs),
+    Top(top::TopArgs),
+    On(on::OnArgs),
+    Serve(serve::ServeArgs),
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
 
-fn main() -> Result<()> {
     // setup tracing for good measure
     let subscriber = FmtSubscriber::builder()
         .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .finish();
 
     tracing::subscriber::set_global_default(subscriber)?;
 
-    // create our hook config
-    let config = win_event_hook::Config::builder()
-        .skip_own_process()
-        .with_dedicated_thread()
-        .with_events(vec![
-            // to see these, try right clicking
-            Event::Named(NamedEvent::ObjectShow),
-            Event::Named(NamedEvent::ObjectHide),
-            // to see this, try moving around the cursor
-            Event::Named(NamedEvent::ObjectLocationChange),
-        ])
-        .finish();
+    match args.command {
+        Some(Command::Record(args)) => record::run(args)?,
+        Some(Command::Replay(args)) => replay::run(args)?,
+        Some(Command::Inspect(args)) => inspect::run(args)?,
+        Some(Command::Merge(args)) => merge::run(args)?,
+        Some(Command::Events(args)) => events::run(args)?,
+        Some(Command::Top(args)) => top::run(args)?,
+        Some(Command::On(args)) => return on::run(args),
+        Some(Command::Serve(args)) => return serve::run(args),
+        None => {
+            let stop = watch(
+                args.hook.config()?,
+                args.output.formatter(),
+                Stopper::new(&args.stop)?,
+                args.metrics_addr,
+            )?;
+
+            return Ok(stop.exit_code());
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints events as they occur, until a stop condition is met or ctrl+c is pressed.
+fn watch(
+    config: Config,
+    formatter: Formatter,
+    stopper: Stopper,
+    metrics_addr: Option<SocketAddr>,
+) -> Result<Stop> {
+    let hook_name = config
+        .dedicated_thread_name
+        .clone()
+        .unwrap_or_else(|| "main".to_string());
+    // process ids cost a syscall per event, so they're only looked up when metrics are served
+    let process_counts = metrics_addr.map(|_| Arc::new(ProcessCounts::default()));
+
+    // print the header of our output, if it has one
+    if let Some(header) = formatter.header() {
+        println!("{header}");
+    }
 
     // and our handler
-    let handler = |ev, _, _, _, _, _| {
-        println!("got event: {:?}", ev);
+    let captured_process_counts = process_counts.clone();
+    let condition = stopper.condition();
+    let handler = move |ev, hwnd, id_object, id_child, id_event_thread, event_time| {
+        let record = EventRecord::new(ev, hwnd, id_object, id_child, id_event_thread, event_time);
+        if !condition.accept(&record) {
+            return;
+        }
+
+        if let Some(process_counts) = &captured_process_counts {
+            process_counts.record(ev, window_process_id(&record.hwnd));
+        }
+
+        // a closed stdout (for instance, when piped into `head`) isn't an error worth reporting
+        let _ = writeln!(io::stdout(), "{}", formatter.format(&record));
     };
 
     // install the hook
-    let mut hook = win_event_hook::WinEventHook::install(config, handler)?;
+    let hook = Arc::new(Mutex::new(win_event_hook::WinEventHook::install(
+        config, handler,
+    )?));
+
+    // serve metrics, if requested
+    if let (Some(metrics_addr), Some(process_counts)) = (metrics_addr, process_counts) {
+        let hook = hook.clone();
+        let server = MetricsServer::bind(metrics_addr, move || {
+            let stats = hook.lock().expect("Unable to obtain hook lock").stats();
+
+            metrics::render(
+                &[HookMetrics {
+                    name: hook_name.clone(),
+                    stats,
+                    processes: process_counts.snapshot(),
+                }],
+                &diagnostics(),
+            )
+        })?;
 
-    // setup ctrlc to help us shutdown neatly when the user hits ctrl+c
-    let running = Arc::new(AtomicBool::new(true));
+        info!(addr = %server.local_addr(), "serving metrics");
+    }
 
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })?;
+    // wait for a stop condition, or ctrl+c
+    let stop = stopper.wait();
 
-    // wait for ctrl+c
-    while running.load(Ordering::SeqCst) {}
+    info!(?stop, "stopping");
 
     // uninstall the hook
-    hook.uninstall()?;
+    hook.lock()
+        .expect("Unable to obtain hook lock")
+        .uninstall()?;
+
+    Ok(stop)
+}
+
+/// Obtains the id of the process that owns a given window, or `0` if it cannot be determined.
+#[cfg(windows)]
+fn window_process_id(hwnd: &WindowHandle) -> u32 {
+    let mut process_id = 0;
+
+    unsafe { GetWindowThreadProcessId(**hwnd, Some(&mut process_id)) };
+
+    process_id
+}
 
-    // exit cleanly
-    Ok(())
+/// Obtains the id of the process that owns a given window, which cannot be determined on this platform.
+#[cfg(not(windows))]
+fn window_process_id(_hwnd: &WindowHandle) -> u32 {
+    0
 }
@@ -0,0 +1,68 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::Args;
+use tracing::warn;
+use win_event_hook::merge::{merge, Merged};
+
+use crate::{args::parse_duration, input::Input, output::OutputArgs};
+
+/// Merges recorded events from several files into one stream, ordered by event time.
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    /// The capture, or JSON Lines, files to merge.
+    #[arg(value_name = "FILE", required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// How far out of order records within a file may be (for instance `250ms`).
+    #[arg(long, value_name = "DURATION", default_value = "250ms", value_parser = parse_duration)]
+    pub window: Duration,
+
+    /// Omits records that are later than the window allows, rather than printing them out of order.
+    #[arg(long)]
+    pub drop_late: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+pub fn run(args: MergeArgs) -> Result<()> {
+    let inputs = args
+        .inputs
+        .iter()
+        .map(|path| Input::open(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let formatter = args.output.formatter();
+    if let Some(header) = formatter.header() {
+        println!("{header}");
+    }
+
+    let mut merged = merge(inputs, args.window);
+
+    for record in merged.by_ref() {
+        if args.drop_late && matches!(record, Merged::Late { .. }) {
+            continue;
+        }
+
+        // a closed stdout (for instance, when piped into `head`) isn't an error worth reporting
+        if writeln!(io::stdout(), "{}", formatter.format(record.record())).is_err() {
+            break;
+        }
+    }
+
+    let late = merged.merger().late();
+    if late > 0 {
+        warn!(
+            late,
+            window = ?args.window,
+            "records were later than the window allows, try a larger --window"
+        );
+    }
+
+    Ok(())
+}
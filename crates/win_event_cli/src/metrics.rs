@@ -0,0 +1,363 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tracing::{debug, warn};
+use win_event_hook::{diagnostics::Diagnostics, events::Event, stats::Stats};
+
+/// Event counts collected by the cli for a single hook, by event and process id.
+#[derive(Default)]
+pub struct ProcessCounts {
+    counts: Mutex<HashMap<(Event, u32), u64>>,
+}
+
+impl ProcessCounts {
+    /// Records an event raised by a given process id.
+    pub fn record(&self, event: Event, process_id: u32) {
+        let mut counts = self.counts.lock().expect("Unable to obtain counts lock");
+
+        *counts.entry((event, process_id)).or_default() += 1;
+    }
+
+    /// Obtains the current counts, as `(event, process_id, count)`.
+    pub fn snapshot(&self) -> Vec<(Event, u32, u64)> {
+        let counts = self.counts.lock().expect("Unable to obtain counts lock");
+
+        counts
+            .iter()
+            .map(|((event, process_id), count)| (*event, *process_id, *count))
+            .collect()
+    }
+}
+
+/// The metrics of a single hook, at a point in time.
+pub struct HookMetrics {
+    /// The name used to label the hook.
+    pub name: String,
+    /// The runtime statistics of the hook.
+    pub stats: Stats,
+    /// The event counts of the hook, as `(event, process_id, count)`.
+    pub processes: Vec<(Event, u32, u64)>,
+}
+
+/// Renders metrics in the Prometheus text exposition format.
+pub fn render(hooks: &[HookMetrics], diagnostics: &Diagnostics) -> String {
+    let mut out = String::new();
+
+    write_metrics(&mut out, hooks, diagnostics).expect("Unable to write to string");
+
+    out
+}
+
+fn write_metrics(
+    out: &mut String,
+    hooks: &[HookMetrics],
+    diagnostics: &Diagnostics,
+) -> fmt::Result {
+    writeln!(
+        out,
+        "# HELP win_event_events_total Events handled by the cli, by event and process."
+    )?;
+    writeln!(out, "# TYPE win_event_events_total counter")?;
+    for hook in hooks {
+        for (event, process_id, count) in &hook.processes {
+            writeln!(
+                out,
+                "win_event_events_total{{hook=\"{}\",event=\"{}\",process=\"{}\"}} {}",
+                escape(&hook.name),
+                event,
+                process_id,
+                count
+            )?;
+        }
+    }
+
+    type Counter = fn(&win_event_hook::stats::EventStats) -> u64;
+    let counters: [(&str, &str, Counter); 5] = [
+        (
+            "win_event_hook_received_total",
+            "Events received by the hook.",
+            |s| s.received,
+        ),
+        (
+            "win_event_hook_filtered_total",
+            "Events discarded by the hook's event filter.",
+            |s| s.filtered,
+        ),
+        (
+            "win_event_hook_delivered_total",
+            "Events delivered to the hook's handler.",
+            |s| s.delivered,
+        ),
+        (
+            "win_event_hook_handler_panics_total",
+            "Events where the hook's handler panicked.",
+            |s| s.handler_panics,
+        ),
+        (
+            "win_event_hook_dropped_total",
+            "Events dropped by the hook's full handoff queue.",
+            |s| s.dropped,
+        ),
+    ];
+
+    for (name, help, value) in counters {
+        writeln!(out, "# HELP {name} {help}")?;
+        writeln!(out, "# TYPE {name} counter")?;
+        for hook in hooks {
+            for (event, stats) in &hook.stats.events {
+                writeln!(
+                    out,
+                    "{name}{{hook=\"{}\",event=\"{}\"}} {}",
+                    escape(&hook.name),
+                    event,
+                    value(stats)
+                )?;
+            }
+        }
+    }
+
+    let name = "win_event_hook_handler_latency_seconds";
+    writeln!(out, "# HELP {name} Time taken by the hook's handler.")?;
+    writeln!(out, "# TYPE {name} histogram")?;
+    for hook in hooks {
+        for (event, stats) in &hook.stats.events {
+            let labels = format!("hook=\"{}\",event=\"{}\"", escape(&hook.name), event);
+            let mut cumulative = 0;
+
+            for (upper_bound, count) in stats.latency.buckets() {
+                cumulative += count;
+
+                // the last bucket is unbounded, so it's written as `+Inf` below
+                if upper_bound == Duration::MAX {
+                    continue;
+                }
+
+                writeln!(
+                    out,
+                    "{name}_bucket{{{labels},le=\"{}\"}} {cumulative}",
+                    upper_bound.as_secs_f64()
+                )?;
+            }
+
+            writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {cumulative}")?;
+            writeln!(
+                out,
+                "{name}_sum{{{labels}}} {}",
+                stats.latency.sum().as_secs_f64()
+            )?;
+            writeln!(out, "{name}_count{{{labels}}} {}", stats.latency.count())?;
+        }
+    }
+
+    let name = "win_event_dropped_events_total";
+    writeln!(
+        out,
+        "# HELP {name} Events that could not be delivered to any hook."
+    )?;
+    writeln!(out, "# TYPE {name} counter")?;
+    writeln!(
+        out,
+        "{name}{{reason=\"unknown_hook\"}} {}",
+        diagnostics.unknown_hook_events
+    )?;
+    writeln!(
+        out,
+        "{name}{{reason=\"released_hook\"}} {}",
+        diagnostics.released_hook_events
+    )?;
+
+    Ok(())
+}
+
+/// Escapes a label value, as required by the Prometheus text exposition format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A minimal HTTP server, exposing metrics at `/metrics`.
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Binds to a given address, serving the output of `render` to each scrape from a background thread.
+    ///
+    /// Note: Each connection is served on its own thread, so an idle client doesn't hold up other scrapes.
+    pub fn bind<F>(addr: SocketAddr, render: F) -> io::Result<Self>
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let render = Arc::new(render);
+
+        thread::Builder::new()
+            .name("MetricsServer".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let render = render.clone();
+                    let result = stream.and_then(|stream| {
+                        thread::Builder::new()
+                            .name("MetricsClient".to_string())
+                            .spawn(move || {
+                                if let Err(err) = respond(stream, render.as_ref()) {
+                                    warn!(?err, "failed to serve metrics");
+                                }
+                            })
+                    });
+
+                    if let Err(err) = result {
+                        warn!(?err, "failed to serve metrics");
+                    }
+                }
+            })?;
+
+        Ok(Self { local_addr })
+    }
+
+    /// The address the server is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+fn respond<F: Fn() -> String>(mut stream: TcpStream, render: &F) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // drain the request headers, which we don't need
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    debug!(request = request_line.trim_end(), "got metrics request");
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", render()),
+        _ => ("404 Not Found", "Not Found\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{Read, Write},
+        net::TcpStream,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    use win_event_hook::{
+        diagnostics::Diagnostics,
+        events::{Event, NamedEvent},
+        stats::{EventStats, LatencyHistogram, Stats},
+    };
+
+    use super::{render, HookMetrics, MetricsServer};
+
+    fn scrape(server: &MetricsServer, path: &str) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn hook_metrics() -> HookMetrics {
+        let event = Event::Named(NamedEvent::ObjectShow);
+        let mut latency = LatencyHistogram::default();
+        latency.record(Duration::from_nanos(100));
+
+        HookMetrics {
+            name: "main".to_string(),
+            stats: Stats {
+                events: HashMap::from([(
+                    event,
+                    EventStats {
+                        received: 2,
+                        filtered: 1,
+                        delivered: 1,
+                        latency,
+                        ..Default::default()
+                    },
+                )]),
+                since: SystemTime::now(),
+            },
+            processes: vec![(event, 42, 1)],
+        }
+    }
+
+    #[test]
+    fn renders_exposition_format() {
+        let diagnostics = Diagnostics {
+            unknown_hook_events: 3,
+            released_hook_events: 0,
+        };
+        let output = render(&[hook_metrics()], &diagnostics);
+
+        assert!(output.contains(
+            "win_event_events_total{hook=\"main\",event=\"ObjectShow\",process=\"42\"} 1\n"
+        ));
+        assert!(output
+            .contains("win_event_hook_received_total{hook=\"main\",event=\"ObjectShow\"} 2\n"));
+        assert!(output
+            .contains("win_event_hook_filtered_total{hook=\"main\",event=\"ObjectShow\"} 1\n"));
+        assert!(output.contains(
+            "win_event_hook_handler_latency_seconds_bucket{hook=\"main\",event=\"ObjectShow\",le=\"0.000000128\"} 1\n"
+        ));
+        assert!(output.contains(
+            "win_event_hook_handler_latency_seconds_count{hook=\"main\",event=\"ObjectShow\"} 1\n"
+        ));
+        // the unbounded last bucket is only written as `+Inf`
+        assert_eq!(output.matches("le=\"+Inf\"").count(), 1);
+        assert!(output.contains("win_event_dropped_events_total{reason=\"unknown_hook\"} 3\n"));
+    }
+
+    #[test]
+    fn serves_metrics_over_loopback() {
+        let server = MetricsServer::bind("127.0.0.1:0".parse().unwrap(), || {
+            render(&[hook_metrics()], &Diagnostics::default())
+        })
+        .unwrap();
+
+        let response = scrape(&server, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(response
+            .contains("win_event_hook_delivered_total{hook=\"main\",event=\"ObjectShow\"} 1\n"));
+
+        let response = scrape(&server, "/other");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        // an idle connection doesn't hold up other scrapes
+        let _idle = TcpStream::connect(server.local_addr()).unwrap();
+        let started = Instant::now();
+        let response = scrape(&server, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,466 @@
+use std::{
+    io::Write,
+    process::{Command, ExitCode, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use clap::{builder::RangedU64ValueParser, Args, ValueEnum};
+use tracing::{debug, error, info, warn};
+use win_event_hook::{jsonl, record::EventRecord, WinEventHook};
+
+use crate::{
+    args::{parse_duration, HookArgs},
+    filter::Filter,
+    output::Column,
+    stop::{StopArgs, Stopper},
+};
+
+/// The prefix of the environment variables describing an event, for instance `WIN_EVENT_HWND`.
+const ENV_PREFIX: &str = "WIN_EVENT_";
+
+/// Runs a command each time an event matches a filter expression.
+#[derive(Args, Debug)]
+pub struct OnArgs {
+    /// The filter expression that events must match (for instance "SystemForeground and object==window").
+    #[arg(value_name = "FILTER")]
+    pub filter: Filter,
+
+    /// The command to run. Placeholders such as `{event}` or `{hwnd}` are replaced with the fields of the event.
+    ///
+    /// The command isn't run by a shell; use `sh -c '...'` or `cmd /c ...` for shell features.
+    #[arg(long, value_name = "COMMAND")]
+    pub exec: Template,
+
+    /// The most commands that may run at once. Events matched while at this limit are skipped.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 4,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_concurrent: usize,
+
+    /// Only runs the command once events have stopped matching for this long, for the latest match.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub debounce: Option<Duration>,
+
+    /// How the matching event is passed to the command, in addition to any placeholders.
+    #[arg(long, value_enum, default_value_t = CommandInput::Env)]
+    pub input: CommandInput,
+
+    #[command(flatten)]
+    pub hook: HookArgs,
+
+    #[command(flatten)]
+    pub stop: StopArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandInput {
+    /// As environment variables, such as `WIN_EVENT_EVENT` and `WIN_EVENT_HWND`.
+    Env,
+    /// As a single line of JSON on stdin, see `win_event_hook::jsonl`.
+    Json,
+    /// Only through placeholders.
+    None,
+}
+
+pub fn run(args: OnArgs) -> Result<ExitCode> {
+    let config = args.hook.config()?;
+    let stopper = Stopper::new(&args.stop)?;
+
+    // matches are handed to a runner, so commands never slow the hook thread
+    let (record_tx, record_rx) = mpsc::channel();
+    let condition = stopper.condition();
+    let filter = args.filter.clone();
+    let handler = move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+        let record = EventRecord::new(
+            event,
+            hwnd,
+            id_object,
+            id_child,
+            id_event_thread,
+            event_time,
+        );
+
+        if filter.matches(&record) && condition.accept(&record) {
+            let _ = record_tx.send(record);
+        }
+    };
+
+    let runner = Runner::new(args.exec, args.input, args.max_concurrent);
+    let debounce = args.debounce.unwrap_or_default();
+    let runner = thread::Builder::new()
+        .name("WinEventCliRunner".to_string())
+        .spawn(move || runner.run(record_rx, debounce))?;
+
+    let mut hook = WinEventHook::install(config, handler)?;
+
+    info!(filter = %args.filter, "running commands on matching events, press ctrl+c to stop");
+
+    let stop = stopper.wait();
+
+    // uninstalling releases the handler, and with it the runner's channel
+    hook.uninstall()?;
+    drop(hook);
+
+    let summary = runner.join().expect("Unable to join runner thread");
+
+    info!(
+        ?stop,
+        started = summary.started,
+        failed = summary.failed,
+        skipped = summary.skipped,
+        "stopped"
+    );
+
+    Ok(stop.exit_code())
+}
+
+/// A part of a command-line argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(Column),
+}
+
+/// A command line, with placeholders for the fields of an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    args: Vec<Vec<Part>>,
+}
+
+impl Template {
+    /// Renders the program and its arguments, for a given record.
+    fn render(&self, record: &EventRecord) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        Part::Literal(literal) => literal.clone(),
+                        Part::Field(column) => column.value(record),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    /// Parses a command line, split on whitespace outside of quotes. `{{` and `}}` are literal braces.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut args = Vec::new();
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut in_arg = false;
+        let mut quote = None;
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                c if Some(c) == quote => quote = None,
+                '"' | '\'' if quote.is_none() => {
+                    quote = Some(c);
+                    in_arg = true;
+                }
+                c if c.is_whitespace() && quote.is_none() => {
+                    if in_arg {
+                        if !literal.is_empty() {
+                            parts.push(Part::Literal(std::mem::take(&mut literal)));
+                        }
+                        args.push(std::mem::take(&mut parts));
+                        in_arg = false;
+                    }
+                }
+                '{' if chars.next_if_eq(&'{').is_some() => {
+                    literal.push('{');
+                    in_arg = true;
+                }
+                '}' if chars.next_if_eq(&'}').is_some() => {
+                    literal.push('}');
+                    in_arg = true;
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(format!(
+                                    "unterminated placeholder '{{{name}' in command '{source}'"
+                                ))
+                            }
+                        }
+                    }
+
+                    let column = Column::from_str(&name, true).map_err(|_| {
+                        format!("unknown placeholder '{{{name}}}' in command '{source}'")
+                    })?;
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(column));
+                    in_arg = true;
+                }
+                c => {
+                    literal.push(c);
+                    in_arg = true;
+                }
+            }
+        }
+
+        if quote.is_some() {
+            return Err(format!("unterminated quote in command '{source}'"));
+        }
+
+        if in_arg {
+            if !literal.is_empty() {
+                parts.push(Part::Literal(literal));
+            }
+            args.push(parts);
+        }
+
+        if args.is_empty() {
+            return Err("the command is empty".to_string());
+        }
+
+        Ok(Self { args })
+    }
+}
+
+/// The number of commands run, by outcome.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    /// Commands that were started.
+    pub started: u64,
+    /// Commands that couldn't be started, or exited unsuccessfully.
+    pub failed: u64,
+    /// Events skipped, as `max_concurrent` commands were already running.
+    pub skipped: u64,
+}
+
+/// Runs commands for matching records, within a concurrency limit.
+struct Runner {
+    template: Template,
+    input: CommandInput,
+    max_concurrent: usize,
+    running: Arc<AtomicUsize>,
+    failed: Arc<AtomicUsize>,
+    children: Vec<JoinHandle<()>>,
+    summary: Summary,
+}
+
+impl Runner {
+    fn new(template: Template, input: CommandInput, max_concurrent: usize) -> Self {
+        Self {
+            template,
+            input,
+            max_concurrent,
+            running: Arc::new(AtomicUsize::new(0)),
+            failed: Arc::new(AtomicUsize::new(0)),
+            children: Vec::new(),
+            summary: Summary::default(),
+        }
+    }
+
+    /// Runs commands for records as they're received, until the sender is dropped.
+    ///
+    /// Note: With a non-zero `debounce`, only the latest of a burst of records is run.
+    fn run(mut self, record_rx: Receiver<EventRecord>, debounce: Duration) -> Summary {
+        let mut pending: Option<(EventRecord, Instant)> = None;
+
+        loop {
+            let received = match &pending {
+                Some((_, deadline)) => {
+                    record_rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => record_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match received {
+                Ok(record) if debounce.is_zero() => self.spawn(&record),
+                Ok(record) => pending = Some((record, Instant::now() + debounce)),
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some((record, _)) = pending.take() {
+                        self.spawn(&record);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    if let Some((record, _)) = pending.take() {
+                        self.spawn(&record);
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        // let running commands finish, rather than orphaning them
+        for child in self.children.drain(..) {
+            let _ = child.join();
+        }
+
+        Summary {
+            failed: self.failed.load(Ordering::SeqCst) as u64,
+            ..self.summary
+        }
+    }
+
+    fn spawn(&mut self, record: &EventRecord) {
+        self.children.retain(|child| !child.is_finished());
+
+        if self.running.load(Ordering::SeqCst) >= self.max_concurrent {
+            self.summary.skipped += 1;
+            warn!(event = %record.event, max_concurrent = self.max_concurrent, "too many commands running, skipping event");
+
+            return;
+        }
+
+        let args = self.template.render(record);
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]);
+
+        match self.input {
+            CommandInput::Env => {
+                for column in Column::value_variants() {
+                    command.env(
+                        format!("{ENV_PREFIX}{}", column.name().to_uppercase()),
+                        column.value(record),
+                    );
+                }
+            }
+            CommandInput::Json => {
+                command.stdin(Stdio::piped());
+            }
+            CommandInput::None => {}
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                self.failed.fetch_add(1, Ordering::SeqCst);
+                error!(command = ?args, %err, "failed to start command");
+
+                return;
+            }
+        };
+
+        self.summary.started += 1;
+        self.running.fetch_add(1, Ordering::SeqCst);
+
+        let line = (self.input == CommandInput::Json).then(|| jsonl::to_line(record));
+        let running = self.running.clone();
+        let failed = self.failed.clone();
+
+        self.children.push(thread::spawn(move || {
+            if let (Some(line), Some(mut stdin)) = (line, child.stdin.take()) {
+                // the command may not read its input, which isn't a failure
+                if let Err(err) = writeln!(stdin, "{line}") {
+                    debug!(command = ?args, %err, "failed to write command input");
+                }
+            }
+
+            match child.wait() {
+                Ok(status) if status.success() => debug!(command = ?args, "command succeeded"),
+                Ok(status) => {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    error!(command = ?args, %status, "command failed");
+                }
+                Err(err) => {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    error!(command = ?args, %err, "failed to wait for command");
+                }
+            }
+
+            running.fetch_sub(1, Ordering::SeqCst);
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use win_event_hook::events::NamedEvent;
+
+    use super::Template;
+    use crate::fixtures::record;
+
+    #[cfg(unix)]
+    fn run(template: &str, records: usize, debounce: u64) -> super::Summary {
+        use std::{sync::mpsc, time::Duration};
+
+        use win_event_hook::record::EventRecord;
+
+        use super::{CommandInput, Runner};
+
+        let (record_tx, record_rx) = mpsc::channel();
+        for thread in 0..records {
+            let record = EventRecord {
+                id_event_thread: thread as u32,
+                ..record(NamedEvent::SystemForeground)
+            };
+            record_tx.send(record).unwrap();
+        }
+        drop(record_tx);
+
+        Runner::new(template.parse().unwrap(), CommandInput::Json, 8)
+            .run(record_rx, Duration::from_millis(debounce))
+    }
+
+    #[test]
+    fn renders_templates() {
+        let record = record(NamedEvent::SystemForeground);
+
+        let template: Template = "script.sh {event} 'hwnd {hwnd}' --thread={thread} {{literal}}"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            template.render(&record),
+            [
+                "script.sh",
+                "SystemForeground",
+                "hwnd 0x00030F24",
+                "--thread=4120",
+                "{literal}"
+            ]
+        );
+
+        assert!("script.sh {window}".parse::<Template>().is_err());
+        assert!("script.sh 'unterminated".parse::<Template>().is_err());
+        assert!("script {event"
+            .parse::<Template>()
+            .unwrap_err()
+            .contains("unterminated placeholder"));
+        assert!("  ".parse::<Template>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_commands_with_failures_and_debounce() {
+        let summary = run("sh -c 'grep -q SystemForeground && exit {thread}'", 3, 0);
+        assert_eq!(
+            (summary.started, summary.failed, summary.skipped),
+            (3, 2, 0)
+        );
+
+        let summary = run("true", 3, 50);
+        assert_eq!((summary.started, summary.failed), (1, 0));
+
+        let summary = run("win-event-cli-missing-command", 1, 0);
+        assert_eq!((summary.started, summary.failed), (0, 1));
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use clap::{Args, ValueEnum};
+use win_event_hook::{events::EventCategory, jsonl, record::EventRecord};
+
+/// Arguments that select how events are printed.
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// How events are printed.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// The fields printed for each event, in order (ignored by `jsonl`, which prints every field).
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "time,event,id,hwnd,object,child,thread"
+    )]
+    pub columns: Vec<Column>,
+
+    /// When table output is colorized.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// An aligned table, with a header row.
+    #[value(alias = "text")]
+    Table,
+    /// One JSON object per event, see `win_event_hook::jsonl`.
+    Jsonl,
+    /// Comma separated values, with a header row.
+    Csv,
+    /// One line of `key=value` pairs per event.
+    Logfmt,
+}
+
+/// A field of an event, which may be printed.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// The time (in milliseconds, since system start) that the event was raised, as in JSON Lines.
+    ///
+    /// Note: This is the raw tick count of the event, rather than a wall-clock timestamp.
+    Time,
+    /// The name of the event.
+    Event,
+    /// The raw id of the event.
+    Id,
+    /// The window that raised the event.
+    Hwnd,
+    /// The object id of the event.
+    Object,
+    /// The child id of the event.
+    Child,
+    /// The thread that raised the event.
+    Thread,
+    /// The sequence number of the event within its hook, if it's known.
+    Seq,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colorize when stdout is a terminal, and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl Column {
+    /// The name of the column, as used in headers and as logfmt keys.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Time => "time",
+            Column::Event => "event",
+            Column::Id => "id",
+            Column::Hwnd => "hwnd",
+            Column::Object => "object",
+            Column::Child => "child",
+            Column::Thread => "thread",
+            Column::Seq => "seq",
+        }
+    }
+
+    /// The width of the column, in table output.
+    fn width(self) -> usize {
+        match self {
+            Column::Time => 10,
+            Column::Event => 28,
+            Column::Id => 10,
+            Column::Hwnd => 10,
+            Column::Object => 6,
+            Column::Child => 6,
+            Column::Thread => 8,
+            Column::Seq => 8,
+        }
+    }
+
+    /// Whether the column is right aligned, in table output.
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Column::Time | Column::Object | Column::Child | Column::Thread | Column::Seq
+        )
+    }
+
+    /// The value of the column, for a given record.
+    pub fn value(self, record: &EventRecord) -> String {
+        match self {
+            Column::Time => record.event_time.to_string(),
+            Column::Event => record.event.to_string(),
+            Column::Id => format!("{:#06X}", u32::from(record.event)),
+            Column::Hwnd => format!("0x{:08X}", record.hwnd.to_raw()),
+            Column::Object => record.id_object.to_string(),
+            Column::Child => record.id_child.to_string(),
+            Column::Thread => record.id_event_thread.to_string(),
+            Column::Seq => record
+                .sequence
+                .map(|sequence| sequence.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Formats records, as selected by [`OutputArgs`].
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    format: OutputFormat,
+    columns: Vec<Column>,
+    color: bool,
+}
+
+impl OutputArgs {
+    /// Creates a [`Formatter`] from the arguments, detecting if stdout supports color.
+    pub fn formatter(&self) -> Formatter {
+        let color = match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        };
+
+        Formatter::new(self.format, self.columns.clone(), color)
+    }
+}
+
+impl Formatter {
+    pub fn new(format: OutputFormat, columns: Vec<Column>, color: bool) -> Self {
+        Self {
+            format,
+            columns,
+            // color is only meaningful for people, so it's limited to table output
+            color: color && format == OutputFormat::Table,
+        }
+    }
+
+    /// The line printed before any records, if the format has one.
+    pub fn header(&self) -> Option<String> {
+        match self.format {
+            OutputFormat::Table => {
+                let line = self
+                    .columns
+                    .iter()
+                    .map(|column| pad(&column.name().to_uppercase(), *column))
+                    .collect::<Vec<_>>()
+                    .join(SEPARATOR);
+
+                Some(paint(line.trim_end(), BOLD, self.color))
+            }
+            OutputFormat::Csv => Some(
+                self.columns
+                    .iter()
+                    .map(|column| column.name())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            OutputFormat::Jsonl | OutputFormat::Logfmt => None,
+        }
+    }
+
+    /// Formats a record as a single line, without a trailing newline.
+    pub fn format(&self, record: &EventRecord) -> String {
+        match self.format {
+            OutputFormat::Table => {
+                let line = self
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        // padding is applied before painting, so escape codes don't skew alignment
+                        let padded = pad(&column.value(record), *column);
+
+                        match self.style(*column, record) {
+                            Some(style) => paint(&padded, style, true),
+                            None => padded,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(SEPARATOR);
+
+                line.trim_end().to_string()
+            }
+            OutputFormat::Jsonl => jsonl::to_line(record),
+            OutputFormat::Csv => self
+                .columns
+                .iter()
+                .map(|column| csv_field(&column.value(record)))
+                .collect::<Vec<_>>()
+                .join(","),
+            OutputFormat::Logfmt => self
+                .columns
+                .iter()
+                .map(|column| format!("{}={}", column.name(), logfmt_value(&column.value(record))))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// The color of a column, if it's colorized.
+    fn style(&self, column: Column, record: &EventRecord) -> Option<&'static str> {
+        if !self.color {
+            return None;
+        }
+
+        match column {
+            Column::Time | Column::Id => Some(DIM),
+            Column::Event => Some(match record.event.category() {
+                EventCategory::Named => CYAN,
+                _ => MAGENTA,
+            }),
+            Column::Hwnd => Some(YELLOW),
+            _ => None,
+        }
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const YELLOW: &str = "\x1b[33m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// The separator between columns, in table output.
+const SEPARATOR: &str = "  ";
+
+/// Aligns a value within its column.
+fn pad(value: &str, column: Column) -> String {
+    let width = column.width();
+
+    match column.is_numeric() {
+        true => format!("{value:>width$}"),
+        false => format!("{value:<width$}"),
+    }
+}
+
+fn paint(value: &str, style: &str, color: bool) -> String {
+    match color {
+        true => format!("{style}{value}{RESET}"),
+        false => value.to_string(),
+    }
+}
+
+/// Quotes a CSV field, if it contains a delimiter, quote or newline.
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Quotes a logfmt value, if it's empty or contains spaces, quotes or `=`.
+fn logfmt_value(value: &str) -> String {
+    match value.is_empty() || value.contains([' ', '"', '=', '\\']) {
+        true => format!("{value:?}"),
+        false => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use win_event_hook::events::NamedEvent;
+
+    use super::{csv_field, Column, Formatter, OutputFormat};
+    use crate::fixtures::record;
+
+    #[test]
+    fn formats_selected_columns() {
+        let columns = vec![Column::Event, Column::Hwnd, Column::Thread];
+
+        let csv = Formatter::new(OutputFormat::Csv, columns.clone(), true);
+        assert_eq!(csv.header().as_deref(), Some("event,hwnd,thread"));
+        assert_eq!(
+            csv.format(&record(NamedEvent::ObjectShow)),
+            "ObjectShow,0x00030F24,4120"
+        );
+
+        let logfmt = Formatter::new(OutputFormat::Logfmt, columns.clone(), true);
+        assert_eq!(logfmt.header(), None);
+        assert_eq!(
+            logfmt.format(&record(NamedEvent::ObjectShow)),
+            "event=ObjectShow hwnd=0x00030F24 thread=4120"
+        );
+
+        let table = Formatter::new(OutputFormat::Table, columns, false);
+        assert_eq!(
+            table.format(&record(NamedEvent::ObjectShow)),
+            format!("{:<28}  0x00030F24      4120", "ObjectShow")
+        );
+
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn colors_only_tables() {
+        let columns = vec![Column::Event];
+
+        let table = Formatter::new(OutputFormat::Table, columns.clone(), true);
+        assert!(table
+            .format(&record(NamedEvent::ObjectShow))
+            .contains('\x1b'));
+
+        let csv = Formatter::new(OutputFormat::Csv, columns, true);
+        assert!(!csv.format(&record(NamedEvent::ObjectShow)).contains('\x1b'));
+    }
+}
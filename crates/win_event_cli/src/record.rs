@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use tracing::{info, warn};
+use win_event_hook::{capture::CaptureWriter, stats::Stats, WinEventHook};
+
+use crate::args::{parse_duration, HookArgs};
+
+/// Records events to a capture file, until ctrl+c is pressed.
+#[derive(Args, Debug)]
+pub struct RecordArgs {
+    /// The capture file to write.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: PathBuf,
+
+    /// How often the capture file is flushed to disk (for instance `500ms` or `5s`).
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    pub flush_interval: Duration,
+
+    #[command(flatten)]
+    pub hook: HookArgs,
+}
+
+pub fn run(args: RecordArgs) -> Result<()> {
+    let config = args.hook.config()?;
+
+    let file = File::create(&args.output)
+        .with_context(|| format!("Unable to create '{}'", args.output.display()))?;
+    let writer = Arc::new(Mutex::new(CaptureWriter::new(
+        BufWriter::new(file),
+        &config,
+    )?));
+
+    // setup ctrlc, so we can stop recording when the user hits ctrl+c
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })?;
+
+    let started = Instant::now();
+    let mut hook = WinEventHook::install(config, CaptureWriter::handler(&writer))?;
+
+    info!(output = %args.output.display(), "recording events, press ctrl+c to stop");
+
+    // flush periodically, until ctrl+c
+    while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(args.flush_interval) {
+        let mut writer = writer.lock().expect("Unable to obtain capture writer lock");
+
+        if let Err(err) = writer.flush() {
+            warn!(?err, "failed to flush capture");
+        }
+    }
+
+    hook.uninstall()?;
+
+    let duration = started.elapsed();
+    let stats = hook.stats();
+
+    // release the hook's handler, and with it the last other reference to the writer
+    drop(hook);
+
+    let writer = Arc::try_unwrap(writer)
+        .ok()
+        .expect("Expected the hook to release the capture writer")
+        .into_inner()
+        .expect("Unable to obtain capture writer lock");
+    let records = writer.records_written();
+    let failed_writes = writer.failed_writes();
+
+    // ensure the capture is durable before reporting success
+    let file = writer
+        .into_inner()?
+        .into_inner()
+        .map_err(|err| err.into_error())?;
+    file.sync_all()?;
+
+    print_summary(records, failed_writes, duration, &stats);
+
+    Ok(())
+}
+
+/// Prints the number of records captured, and the totals of each event.
+fn print_summary(records: u64, failed_writes: u64, duration: Duration, stats: &Stats) {
+    println!(
+        "recorded {records} events in {:.1}s",
+        duration.as_secs_f64()
+    );
+
+    if failed_writes > 0 {
+        println!("failed to record {failed_writes} events");
+    }
+
+    let mut totals = stats
+        .events
+        .iter()
+        .map(|(event, stats)| (event.to_string(), stats.delivered))
+        .filter(|(_, delivered)| *delivered > 0)
+        .collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    for (event, delivered) in totals {
+        println!("{delivered:>10}  {event}");
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use clap::Args;
+use tracing::info;
+use win_event_hook::{
+    record::EventRecord,
+    replay::{Pacing, ReplayStep, Replayer},
+};
+
+use crate::{input::Input, output::OutputArgs};
+
+/// Replays recorded events, as though they were raised by the os.
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// The capture, or JSON Lines, file to replay.
+    #[arg(value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Replays events this many times faster than they were recorded.
+    #[arg(long, value_name = "MULTIPLIER", conflicts_with_all = ["fast", "step"])]
+    pub speed: Option<f64>,
+
+    /// Replays events as fast as possible.
+    #[arg(long, conflicts_with = "step")]
+    pub fast: bool,
+
+    /// Replays a single event each time enter is pressed.
+    #[arg(long)]
+    pub step: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+pub fn run(args: ReplayArgs) -> Result<()> {
+    let input = Input::open(&args.input)?;
+
+    // captures are filtered as they were when recorded, other records are replayed as-is
+    let config = input
+        .header()
+        .map(|header| header.config.clone())
+        .unwrap_or_default();
+
+    let formatter = args.output.formatter();
+    if let Some(header) = formatter.header() {
+        println!("{header}");
+    }
+
+    let handler = |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+        let record = EventRecord::new(
+            event,
+            hwnd,
+            id_object,
+            id_child,
+            id_event_thread,
+            event_time,
+        );
+        // a closed stdout (for instance, when piped into `head`) isn't an error worth reporting
+        let _ = writeln!(io::stdout(), "{}", formatter.format(&record));
+    };
+
+    let mut replayer = Replayer::new(&config, input);
+
+    let summary = if args.step {
+        let mut lines = io::stdin().lock().lines();
+
+        loop {
+            match replayer.step(&handler) {
+                Some(ReplayStep::Delivered(_)) => {
+                    if !matches!(lines.next(), Some(Ok(_))) {
+                        break;
+                    }
+                }
+                Some(ReplayStep::Filtered(_)) => {}
+                None => break,
+            }
+        }
+
+        replayer.summary()
+    } else {
+        let pacing = match (args.fast, args.speed) {
+            (true, _) => Pacing::AsFastAsPossible,
+            (false, Some(speed)) => Pacing::Speed(speed),
+            (false, None) => Pacing::Original,
+        };
+
+        replayer = replayer.with_pacing(pacing);
+        replayer.run(&handler)
+    };
+
+    info!(
+        delivered = summary.delivered,
+        filtered = summary.filtered,
+        handler_panics = summary.handler_panics,
+        "replay complete"
+    );
+
+    Ok(())
+}
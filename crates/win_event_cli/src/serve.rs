@@ -0,0 +1,635 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    process::ExitCode,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+#[cfg(not(unix))]
+use anyhow::bail;
+use anyhow::{Context, Result};
+use clap::{builder::RangedU64ValueParser, Args, ValueEnum};
+use tracing::{debug, info, warn};
+use win_event_hook::{jsonl, record::EventRecord, WinEventHook};
+
+use crate::{
+    args::HookArgs,
+    filter::Filter,
+    stop::{StopArgs, Stopper},
+};
+
+/// How long a client has, after connecting, to send a filter expression.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long clients have, once the server stops, to finish sending their queued records.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Streams events, as JSON Lines, to clients of a local socket.
+///
+/// On connect, a client may send a single line with a filter expression (for instance
+/// "ObjectShow and object==window") to only receive matching events. An empty line, or
+/// sending nothing, receives every event. An invalid filter is answered with a line of
+/// JSON, such as `{"error":"..."}`, and the connection is closed. Events are streamed
+/// from once the filter is received (or the client has sent nothing for 500ms).
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// The loopback address (for instance `127.0.0.1:9185`), or unix socket (for instance `unix:/tmp/events.sock`), to listen on.
+    #[arg(long, value_name = "ENDPOINT")]
+    pub listen: Endpoint,
+
+    /// The number of events queued for each client, before it's considered slow.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1024,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub buffer: usize,
+
+    /// What happens to clients that can't keep up.
+    #[arg(long, value_enum, default_value_t = SlowClient::Drop)]
+    pub slow_client: SlowClient,
+
+    #[command(flatten)]
+    pub hook: HookArgs,
+
+    #[command(flatten)]
+    pub stop: StopArgs,
+}
+
+/// Where the server listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            return match path.is_empty() {
+                true => Err("expected a path after 'unix:'".to_string()),
+                false => Ok(Endpoint::Unix(PathBuf::from(path))),
+            };
+        }
+
+        let addr: SocketAddr = value.parse().map_err(|_| {
+            format!("'{value}' is not an address (such as 127.0.0.1:9185) or unix:PATH")
+        })?;
+
+        // events describe the user's desktop, so they aren't offered to other machines
+        if !addr.ip().is_loopback() {
+            return Err(format!("'{value}' is not a loopback address"));
+        }
+
+        Ok(Endpoint::Tcp(addr))
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{addr}"),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowClient {
+    /// Drops events for the client, until it catches up.
+    Drop,
+    /// Disconnects the client.
+    Disconnect,
+}
+
+pub fn run(args: ServeArgs) -> Result<ExitCode> {
+    let config = args.hook.config()?;
+    let stopper = Stopper::new(&args.stop)?;
+
+    let broadcaster = Arc::new(Broadcaster::new(args.buffer, args.slow_client));
+    let listener = Listener::bind(&args.listen)?;
+    listener.spawn(broadcaster.clone())?;
+
+    let condition = stopper.condition();
+    let handler = {
+        let broadcaster = broadcaster.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            if condition.accept(&record) {
+                broadcaster.broadcast(&record);
+            }
+        }
+    };
+
+    let mut hook = WinEventHook::install(config, handler)?;
+
+    info!(listen = %args.listen, "streaming events, press ctrl+c to stop");
+
+    let stop = stopper.wait();
+
+    hook.uninstall()?;
+    broadcaster.close();
+    broadcaster.join(DRAIN_TIMEOUT);
+
+    if let Endpoint::Unix(path) = &args.listen {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let stats = broadcaster.stats();
+    info!(
+        ?stop,
+        clients = stats.clients,
+        rejected = stats.rejected,
+        sent = stats.sent,
+        dropped = stats.dropped,
+        disconnected = stats.disconnected,
+        "stopped"
+    );
+
+    Ok(stop.exit_code())
+}
+
+/// Totals of the server, across every client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ServeStats {
+    /// Clients that connected.
+    pub clients: u64,
+    /// Clients that sent an invalid filter.
+    pub rejected: u64,
+    /// Events sent to clients.
+    pub sent: u64,
+    /// Events dropped, as clients' queues were full.
+    pub dropped: u64,
+    /// Clients disconnected, as their queues were full.
+    pub disconnected: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    clients: AtomicU64,
+    rejected: AtomicU64,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+/// A connected client, as seen by the hook thread.
+struct Client {
+    id: u64,
+    /// Only matching records are queued, so the queue only fills with records the client receives.
+    filter: Option<Filter>,
+    record_tx: SyncSender<EventRecord>,
+    /// Used to unblock the client's thread, when it's disconnected for being slow.
+    stream: Option<Stream>,
+}
+
+/// Hands records to every client, without ever blocking.
+pub struct Broadcaster {
+    buffer: usize,
+    slow_client: SlowClient,
+    clients: Mutex<Vec<Client>>,
+    /// Set once the server stops, so clients still sending their filter aren't subscribed.
+    closed: AtomicBool,
+    /// The threads serving each client, so the server can wait for them to finish.
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    counters: Counters,
+}
+
+impl Broadcaster {
+    pub fn new(buffer: usize, slow_client: SlowClient) -> Self {
+        Self {
+            buffer,
+            slow_client,
+            clients: Mutex::new(Vec::new()),
+            closed: AtomicBool::new(false),
+            threads: Mutex::new(Vec::new()),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Adds a client, with an optional filter, returning the records queued for it.
+    ///
+    /// Note: Once the broadcaster is closed, clients are no longer added.
+    fn subscribe(
+        &self,
+        id: u64,
+        stream: Option<Stream>,
+        filter: Option<Filter>,
+    ) -> Option<Receiver<EventRecord>> {
+        let (record_tx, record_rx) = mpsc::sync_channel(self.buffer);
+        let mut clients = self.clients.lock().expect("Unable to obtain clients lock");
+
+        // checked while locked, so a client can't be added after `close` clears them
+        if self.closed.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        clients.push(Client {
+            id,
+            filter,
+            record_tx,
+            stream,
+        });
+
+        Some(record_rx)
+    }
+
+    /// Queues a record for every client, dropping it (or the client) where a queue is full.
+    pub fn broadcast(&self, record: &EventRecord) {
+        let mut clients = self.clients.lock().expect("Unable to obtain clients lock");
+
+        clients.retain(|client| {
+            if client
+                .filter
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(record))
+            {
+                return true;
+            }
+
+            match client.record_tx.try_send(record.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => match self.slow_client {
+                    SlowClient::Drop => {
+                        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+
+                        true
+                    }
+                    SlowClient::Disconnect => {
+                        self.counters.disconnected.fetch_add(1, Ordering::Relaxed);
+                        warn!(client = client.id, "disconnecting slow client");
+
+                        if let Some(stream) = &client.stream {
+                            let _ = stream.shutdown();
+                        }
+
+                        false
+                    }
+                },
+                // the client's thread has exited, as the client went away
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+
+    /// Removes every client, letting them finish sending their queued records.
+    pub fn close(&self) {
+        let mut clients = self.clients.lock().expect("Unable to obtain clients lock");
+
+        self.closed.store(true, Ordering::SeqCst);
+        clients.clear();
+    }
+
+    /// Waits, up to a given timeout, for every client to finish sending its queued records.
+    ///
+    /// Note: This is for use after [`Self::close`], as clients otherwise keep receiving records.
+    pub fn join(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let threads =
+            std::mem::take(&mut *self.threads.lock().expect("Unable to obtain threads lock"));
+        let mut unfinished = 0;
+
+        for thread in threads {
+            while !thread.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            match thread.is_finished() {
+                true => {
+                    let _ = thread.join();
+                }
+                false => unfinished += 1,
+            }
+        }
+
+        if unfinished > 0 {
+            warn!(
+                clients = unfinished,
+                ?timeout,
+                "clients did not finish sending their queued records"
+            );
+        }
+    }
+
+    /// Tracks the thread serving a client, so [`Self::join`] can wait for it.
+    fn track(&self, thread: JoinHandle<()>) {
+        let mut threads = self.threads.lock().expect("Unable to obtain threads lock");
+
+        // finished threads are forgotten, so long running servers don't accumulate them
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(thread);
+    }
+
+    pub fn stats(&self) -> ServeStats {
+        ServeStats {
+            clients: self.counters.clients.load(Ordering::SeqCst),
+            rejected: self.counters.rejected.load(Ordering::SeqCst),
+            sent: self.counters.sent.load(Ordering::SeqCst),
+            dropped: self.counters.dropped.load(Ordering::SeqCst),
+            disconnected: self.counters.disconnected.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// A connection to a client.
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(Shutdown::Both),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Accepts clients, on either a loopback tcp address or a unix socket.
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn bind(endpoint: &Endpoint) -> Result<Self> {
+        match endpoint {
+            Endpoint::Tcp(addr) => Ok(Listener::Tcp(
+                TcpListener::bind(addr).with_context(|| format!("Unable to listen on {addr}"))?,
+            )),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(Listener::Unix(
+                UnixListener::bind(path)
+                    .with_context(|| format!("Unable to listen on '{}'", path.display()))?,
+            )),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => bail!("Unix sockets are not supported on this platform"),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.accept().map(|(stream, _)| Stream::Unix(stream)),
+        }
+    }
+
+    /// Accepts clients on a background thread, serving each from a thread of its own.
+    fn spawn(self, broadcaster: Arc<Broadcaster>) -> Result<()> {
+        thread::Builder::new()
+            .name("WinEventCliListener".to_string())
+            .spawn(move || loop {
+                let stream = match self.accept() {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!(%err, "failed to accept client");
+                        continue;
+                    }
+                };
+
+                let id = broadcaster.counters.clients.fetch_add(1, Ordering::SeqCst);
+                let client_broadcaster = broadcaster.clone();
+
+                let spawned = thread::Builder::new()
+                    .name(format!("WinEventCliClient{id}"))
+                    .spawn(
+                        move || match serve_client(id, stream, &client_broadcaster) {
+                            Ok(()) => debug!(client = id, "client finished"),
+                            Err(err) => debug!(client = id, %err, "client disconnected"),
+                        },
+                    );
+
+                match spawned {
+                    Ok(thread) => broadcaster.track(thread),
+                    Err(err) => warn!(%err, "failed to serve client"),
+                }
+            })?;
+
+        Ok(())
+    }
+}
+
+/// Reads a client's filter, then writes matching records until either side goes away.
+fn serve_client(id: u64, stream: Stream, broadcaster: &Broadcaster) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    let filter = match reader.read_line(&mut line) {
+        Ok(_) => line.trim(),
+        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => "",
+        Err(err) => return Err(err),
+    };
+
+    let shutdown = stream.try_clone().ok();
+    let mut writer = BufWriter::new(stream);
+
+    let filter = match filter.is_empty() {
+        true => None,
+        false => match filter.parse::<Filter>() {
+            Ok(filter) => Some(filter),
+            Err(err) => {
+                broadcaster.counters.rejected.fetch_add(1, Ordering::SeqCst);
+                writeln!(writer, "{}", serde_json::json!({ "error": err }))?;
+
+                return writer.flush();
+            }
+        },
+    };
+
+    let Some(record_rx) = broadcaster.subscribe(id, shutdown, filter) else {
+        return Ok(());
+    };
+
+    while let Ok(mut record) = record_rx.recv() {
+        // write everything that's queued, before flushing
+        loop {
+            writeln!(writer, "{}", jsonl::to_line(&record))?;
+            broadcaster.counters.sent.fetch_add(1, Ordering::Relaxed);
+
+            record = match record_rx.try_recv() {
+                Ok(record) => record,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            };
+        }
+
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use win_event_hook::events::NamedEvent;
+
+    use super::{Broadcaster, Endpoint, Listener, SlowClient};
+    use crate::{filter::Filter, fixtures::record};
+
+    #[test]
+    fn parses_loopback_endpoints() {
+        assert!(matches!("127.0.0.1:9185".parse(), Ok(Endpoint::Tcp(_))));
+        assert!(matches!("[::1]:9185".parse(), Ok(Endpoint::Tcp(_))));
+        assert!(matches!(
+            "unix:/tmp/events.sock".parse(),
+            Ok(Endpoint::Unix(_))
+        ));
+        assert!("0.0.0.0:9185".parse::<Endpoint>().is_err());
+        assert!("unix:".parse::<Endpoint>().is_err());
+    }
+
+    #[test]
+    fn drops_or_disconnects_slow_clients() {
+        let broadcaster = Broadcaster::new(2, SlowClient::Drop);
+        let record_rx = broadcaster.subscribe(0, None, None).unwrap();
+
+        for _ in 0..5 {
+            broadcaster.broadcast(&record(NamedEvent::ObjectShow));
+        }
+        assert_eq!(broadcaster.stats().dropped, 3);
+        assert_eq!(record_rx.try_iter().count(), 2);
+
+        // records a client's filter rejects don't fill its queue
+        let broadcaster = Broadcaster::new(2, SlowClient::Disconnect);
+        let filter = "ObjectHide".parse::<Filter>().unwrap();
+        let record_rx = broadcaster.subscribe(0, None, Some(filter)).unwrap();
+
+        for _ in 0..5 {
+            broadcaster.broadcast(&record(NamedEvent::ObjectShow));
+        }
+        broadcaster.broadcast(&record(NamedEvent::ObjectHide));
+        assert_eq!(broadcaster.stats().disconnected, 0);
+        assert_eq!(record_rx.try_iter().count(), 1);
+
+        let broadcaster = Broadcaster::new(2, SlowClient::Disconnect);
+        let record_rx = broadcaster.subscribe(0, None, None).unwrap();
+
+        for _ in 0..5 {
+            broadcaster.broadcast(&record(NamedEvent::ObjectShow));
+        }
+        assert_eq!(broadcaster.stats().disconnected, 1);
+        assert_eq!(record_rx.iter().count(), 2);
+    }
+
+    #[test]
+    fn streams_filtered_records_to_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let broadcaster = Arc::new(Broadcaster::new(16, SlowClient::Drop));
+        Listener::Tcp(listener).spawn(broadcaster.clone()).unwrap();
+
+        let mut filtered = TcpStream::connect(addr).unwrap();
+        writeln!(filtered, "ObjectShow").unwrap();
+        let mut invalid = TcpStream::connect(addr).unwrap();
+        writeln!(invalid, "NotAnEvent").unwrap();
+
+        // wait for the valid client to be subscribed, and the invalid one rejected
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while (broadcaster.clients.lock().unwrap().is_empty() || broadcaster.stats().rejected < 1)
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        broadcaster.broadcast(&record(NamedEvent::ObjectHide));
+        broadcaster.broadcast(&record(NamedEvent::ObjectShow));
+        broadcaster.close();
+        broadcaster.join(Duration::from_secs(5));
+        assert_eq!(broadcaster.stats().sent, 1);
+
+        let lines = BufReader::new(filtered)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains(r#""event":"ObjectShow""#));
+
+        let lines = BufReader::new(invalid)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with(r#"{"error":"#));
+        assert_eq!(broadcaster.stats().rejected, 1);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::Args;
+use win_event_hook::record::EventRecord;
+
+use crate::{args::parse_duration, filter::Filter};
+
+/// The exit code when `--duration` elapses before `--count` or `--until` are met.
+pub const EXIT_TIMED_OUT: u8 = 124;
+
+/// The exit code when ctrl+c is pressed.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Arguments that select when watching events stops.
+#[derive(Args, Debug, Clone)]
+pub struct StopArgs {
+    /// Exits after this many events.
+    #[arg(long, value_name = "N")]
+    pub count: Option<u64>,
+
+    /// Exits after this long (for instance `30s`). If `--count` or `--until` are also used, this is a timeout.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// Exits once an event matches a filter expression (for instance "ObjectShow and object==window").
+    #[arg(long, value_name = "FILTER")]
+    pub until: Option<Filter>,
+}
+
+/// Why watching events stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// `--count` events were seen, or an event matched `--until`.
+    Matched,
+    /// `--duration` elapsed, without `--count` or `--until`.
+    Elapsed,
+    /// `--duration` elapsed before `--count` or `--until` were met.
+    TimedOut,
+    /// Ctrl+c was pressed.
+    Interrupted,
+}
+
+impl Stop {
+    /// The exit code of the process, when stopped for this reason.
+    ///
+    /// Note: Errors exit with `1` (or `2`, for invalid arguments).
+    pub fn exit_code(self) -> ExitCode {
+        match self {
+            Stop::Matched | Stop::Elapsed => ExitCode::SUCCESS,
+            Stop::TimedOut => ExitCode::from(EXIT_TIMED_OUT),
+            Stop::Interrupted => ExitCode::from(EXIT_INTERRUPTED),
+        }
+    }
+}
+
+/// Determines when `--count` or `--until` are met, as events are handled.
+#[derive(Debug)]
+pub struct StopCondition {
+    count: Option<u64>,
+    until: Option<Filter>,
+    seen: AtomicU64,
+    stopped: AtomicBool,
+    stop_tx: Sender<Stop>,
+}
+
+impl StopCondition {
+    /// Determines if a record should be handled, signaling a stop once the condition is met.
+    ///
+    /// Note: The record that meets the condition is handled, and none after it are.
+    pub fn accept(&self, record: &EventRecord) -> bool {
+        if self.stopped.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let seen = self.seen.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let matched = self.count.is_some_and(|count| seen >= count)
+            || self
+                .until
+                .as_ref()
+                .is_some_and(|until| until.matches(record));
+
+        if matched && !self.stopped.swap(true, Ordering::SeqCst) {
+            let _ = self.stop_tx.send(Stop::Matched);
+        }
+
+        true
+    }
+}
+
+/// Waits, without spinning, for a [`StopCondition`], `--duration` or ctrl+c.
+pub struct Stopper {
+    condition: Arc<StopCondition>,
+    duration: Option<Duration>,
+    stop_rx: Receiver<Stop>,
+}
+
+impl Stopper {
+    /// Creates a stopper, handling ctrl+c.
+    pub fn new(args: &StopArgs) -> Result<Self> {
+        let stopper = Self::without_signals(args);
+
+        let stop_tx = stopper.condition.stop_tx.clone();
+        ctrlc::set_handler(move || {
+            let _ = stop_tx.send(Stop::Interrupted);
+        })?;
+
+        Ok(stopper)
+    }
+
+    fn without_signals(args: &StopArgs) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel();
+
+        Self {
+            condition: Arc::new(StopCondition {
+                count: args.count,
+                until: args.until.clone(),
+                seen: AtomicU64::new(0),
+                stopped: AtomicBool::new(false),
+                stop_tx,
+            }),
+            duration: args.duration,
+            stop_rx,
+        }
+    }
+
+    /// The condition, to be checked as events are handled.
+    pub fn condition(&self) -> Arc<StopCondition> {
+        self.condition.clone()
+    }
+
+    /// Blocks until watching should stop.
+    pub fn wait(&self) -> Stop {
+        let stop = match self.duration {
+            Some(duration) => match self.stop_rx.recv_timeout(duration) {
+                Ok(stop) => stop,
+                Err(RecvTimeoutError::Timeout) => {
+                    match self.condition.count.is_some() || self.condition.until.is_some() {
+                        true => Stop::TimedOut,
+                        false => Stop::Elapsed,
+                    }
+                }
+                // the condition holds a sender, so this can't disconnect
+                Err(RecvTimeoutError::Disconnected) => Stop::Interrupted,
+            },
+            None => self.stop_rx.recv().unwrap_or(Stop::Interrupted),
+        };
+
+        // stop handling events that arrive before the hook is uninstalled
+        self.condition.stopped.store(true, Ordering::SeqCst);
+
+        stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use win_event_hook::events::NamedEvent;
+
+    use super::{Stop, StopArgs, Stopper};
+    use crate::fixtures::record;
+
+    fn args(count: Option<u64>, until: Option<&str>, duration: Option<u64>) -> StopArgs {
+        StopArgs {
+            count,
+            duration: duration.map(Duration::from_millis),
+            until: until.map(|until| until.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn stops_at_count() {
+        let stopper = Stopper::without_signals(&args(Some(2), None, None));
+        let condition = stopper.condition();
+
+        assert!(condition.accept(&record(NamedEvent::ObjectShow)));
+        assert!(condition.accept(&record(NamedEvent::ObjectShow)));
+        assert!(!condition.accept(&record(NamedEvent::ObjectShow)));
+        assert_eq!(stopper.wait(), Stop::Matched);
+    }
+
+    #[test]
+    fn stops_until_matched_or_timed_out() {
+        let stopper = Stopper::without_signals(&args(None, Some("ObjectShow"), Some(10)));
+        let condition = stopper.condition();
+
+        assert!(condition.accept(&record(NamedEvent::ObjectHide)));
+        assert_eq!(stopper.wait(), Stop::TimedOut);
+        assert!(!condition.accept(&record(NamedEvent::ObjectShow)));
+
+        let stopper = Stopper::without_signals(&args(None, Some("ObjectShow"), Some(10_000)));
+        let condition = stopper.condition();
+
+        assert!(condition.accept(&record(NamedEvent::ObjectHide)));
+        assert!(condition.accept(&record(NamedEvent::ObjectShow)));
+        assert_eq!(stopper.wait(), Stop::Matched);
+
+        let stopper = Stopper::without_signals(&args(None, None, Some(10)));
+        assert_eq!(stopper.wait(), Stop::Elapsed);
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    hash::Hash,
+    io::{self, IsTerminal, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use clap::Args;
+use crossterm::{
+    cursor,
+    event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue, terminal,
+};
+use win_event_hook::{
+    events::Event, handler::EventHandler, record::EventRecord, stats::EventStats, WinEventHook,
+};
+
+use crate::args::{parse_duration, HookArgs};
+
+/// Shows a continuously refreshing view of the events being raised, and the windows and threads raising them.
+#[derive(Args, Debug)]
+pub struct TopArgs {
+    /// How often the view is refreshed (for instance `500ms` or `2s`).
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    pub interval: Duration,
+
+    /// The number of events, windows and threads to list.
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+
+    #[command(flatten)]
+    pub hook: HookArgs,
+}
+
+pub fn run(args: TopArgs) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("top requires an interactive terminal");
+    }
+
+    let config = args.hook.config()?;
+    let aggregator = Arc::new(Mutex::new(Aggregator::new(config.event_filter.as_deref())));
+
+    let mut hook = WinEventHook::install(config, Aggregator::handler(&aggregator))?;
+
+    let result = Screen::enter().and_then(|screen| {
+        let mut view = View::new(args.top);
+        // while paused, a copy of the aggregation is shown, so sorting still applies
+        let mut frozen: Option<(Aggregator, EventStats)> = None;
+        let mut sampled = Instant::now();
+        let mut sample = true;
+
+        loop {
+            let mut current = aggregator.lock().expect("Unable to obtain aggregator lock");
+
+            // samples are taken even while paused, so rates are correct once resumed
+            if sample {
+                current.sample(sampled.elapsed());
+                sampled = Instant::now();
+            }
+
+            let out = match &frozen {
+                Some((aggregator, totals)) => view.render(aggregator, totals)?,
+                None => view.render(&current, &hook.stats().total())?,
+            };
+            drop(current);
+
+            screen.draw(&out)?;
+
+            // wait for the next sample, handling keys as they're pressed
+            sample = true;
+            let deadline = Instant::now() + args.interval;
+            while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+                if !event::poll(timeout)? {
+                    break;
+                }
+
+                if let TermEvent::Key(key) = event::read()? {
+                    match view.handle(key) {
+                        Action::Quit => return Ok(()),
+                        Action::Redraw => {
+                            frozen = match view.paused {
+                                true => frozen.or_else(|| {
+                                    let current = aggregator
+                                        .lock()
+                                        .expect("Unable to obtain aggregator lock");
+
+                                    Some((current.clone(), hook.stats().total()))
+                                }),
+                                false => None,
+                            };
+                            sample = false;
+
+                            break;
+                        }
+                        Action::None => {}
+                    }
+                }
+            }
+        }
+    });
+
+    hook.uninstall()?;
+
+    result
+}
+
+/// Owns the terminal while the view is shown, restoring it when dropped.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(Self)
+    }
+
+    fn draw(&self, out: &str) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+
+        queue!(
+            stdout,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+
+        // raw mode doesn't translate newlines, so each line returns the cursor itself
+        for line in out.lines() {
+            write!(stdout, "{line}\r\n")?;
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// How rows are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// By events per second, over the latest interval.
+    Rate,
+    /// By events since the view started.
+    Total,
+    /// By name.
+    Name,
+}
+
+impl Sort {
+    /// The next sort, when cycling through them.
+    fn next(self) -> Self {
+        match self {
+            Sort::Rate => Sort::Total,
+            Sort::Total => Sort::Name,
+            Sort::Name => Sort::Rate,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Sort::Rate => "rate",
+            Sort::Total => "total",
+            Sort::Name => "name",
+        }
+    }
+}
+
+/// What should happen after a key is pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    None,
+    Redraw,
+    Quit,
+}
+
+/// The interactive state of the view.
+#[derive(Debug)]
+struct View {
+    top: usize,
+    sort: Sort,
+    paused: bool,
+}
+
+impl View {
+    fn new(top: usize) -> Self {
+        Self {
+            top,
+            sort: Sort::Rate,
+            paused: false,
+        }
+    }
+
+    fn handle(&mut self, key: KeyEvent) -> Action {
+        if key.kind != KeyEventKind::Press {
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            // raw mode captures ctrl+c, so it's handled as a key
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            KeyCode::Char('p') | KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Char('s') | KeyCode::Tab => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.sort = Sort::Rate,
+            KeyCode::Char('t') => self.sort = Sort::Total,
+            KeyCode::Char('n') => self.sort = Sort::Name,
+            _ => return Action::None,
+        }
+
+        Action::Redraw
+    }
+
+    fn render(&self, aggregator: &Aggregator, totals: &EventStats) -> Result<String, fmt::Error> {
+        let mut out = String::new();
+
+        writeln!(
+            out,
+            "win_event_cli top - {:.0}s, sorted by {}{}    [p]ause [s]ort [r]ate [t]otal [n]ame [q]uit",
+            aggregator.elapsed.as_secs_f64(),
+            self.sort.name(),
+            match self.paused {
+                true => " (paused)",
+                false => "",
+            }
+        )?;
+        writeln!(
+            out,
+            "received {}, filtered {}, delivered {}, dropped {}, handler panics {}",
+            totals.received,
+            totals.filtered,
+            totals.delivered,
+            totals.dropped,
+            totals.handler_panics
+        )?;
+
+        writeln!(out, "\n{:<32} {:>10} {:>12}", "EVENT", "PER SEC", "TOTAL")?;
+        for row in aggregator.events(self.sort, self.top) {
+            writeln!(
+                out,
+                "{:<32} {:>10.1} {:>12}",
+                row.label, row.rate, row.total
+            )?;
+        }
+
+        writeln!(out, "\n{:<32} {:>10} {:>12}", "WINDOW", "PER SEC", "TOTAL")?;
+        for row in aggregator.windows(self.sort, self.top) {
+            writeln!(
+                out,
+                "{:<32} {:>10.1} {:>12}",
+                row.label, row.rate, row.total
+            )?;
+        }
+
+        writeln!(out, "\n{:<32} {:>10} {:>12}", "THREAD", "PER SEC", "TOTAL")?;
+        for row in aggregator.threads(self.sort, self.top) {
+            writeln!(
+                out,
+                "{:<32} {:>10.1} {:>12}",
+                row.label, row.rate, row.total
+            )?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// The count of records with a particular key.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Counter {
+    /// Records since the latest sample.
+    pending: u64,
+    /// Records per second, in the interval before the latest sample.
+    rate: f64,
+    /// Records since aggregation started.
+    total: u64,
+}
+
+impl Counter {
+    fn record(&mut self) {
+        self.pending += 1;
+        self.total += 1;
+    }
+
+    fn sample(&mut self, elapsed: Duration) {
+        self.rate = match elapsed.is_zero() {
+            true => 0.0,
+            false => self.pending as f64 / elapsed.as_secs_f64(),
+        };
+        self.pending = 0;
+    }
+}
+
+/// A single row of the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub label: String,
+    pub rate: f64,
+    pub total: u64,
+}
+
+/// Aggregates records into rates and totals by event, window and thread.
+#[derive(Debug, Default, Clone)]
+pub struct Aggregator {
+    events: HashMap<Event, Counter>,
+    windows: HashMap<usize, Counter>,
+    threads: HashMap<u32, Counter>,
+    /// The time covered by all samples.
+    elapsed: Duration,
+}
+
+impl Aggregator {
+    /// Creates a new aggregator, listing every event of an `event_filter` (even before they're raised).
+    pub fn new(event_filter: Option<&[Event]>) -> Self {
+        let events = event_filter
+            .unwrap_or_default()
+            .iter()
+            .map(|event| (*event, Counter::default()))
+            .collect();
+
+        Self {
+            events,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an [`EventHandler`] that aggregates events as they're received.
+    pub fn handler(aggregator: &Arc<Mutex<Self>>) -> impl EventHandler {
+        let aggregator = aggregator.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            aggregator
+                .lock()
+                .expect("Unable to obtain aggregator lock")
+                .record(&record);
+        }
+    }
+
+    /// Adds a record to the aggregation.
+    pub fn record(&mut self, record: &EventRecord) {
+        self.events.entry(record.event).or_default().record();
+        self.windows
+            .entry(record.hwnd.to_raw())
+            .or_default()
+            .record();
+        self.threads
+            .entry(record.id_event_thread)
+            .or_default()
+            .record();
+    }
+
+    /// Ends the current interval, which lasted for `elapsed`, updating the rates of every row.
+    pub fn sample(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed;
+
+        self.events
+            .values_mut()
+            .chain(self.windows.values_mut())
+            .chain(self.threads.values_mut())
+            .for_each(|counter| counter.sample(elapsed));
+    }
+
+    /// The `n` first events, in a given order.
+    pub fn events(&self, sort: Sort, n: usize) -> Vec<Row> {
+        rows(&self.events, |event| event.to_string(), sort, n)
+    }
+
+    /// The `n` first windows, in a given order.
+    pub fn windows(&self, sort: Sort, n: usize) -> Vec<Row> {
+        rows(&self.windows, |hwnd| format!("0x{hwnd:08X}"), sort, n)
+    }
+
+    /// The `n` first threads, in a given order.
+    pub fn threads(&self, sort: Sort, n: usize) -> Vec<Row> {
+        rows(&self.threads, |thread| thread.to_string(), sort, n)
+    }
+}
+
+fn rows<K: Eq + Hash>(
+    counters: &HashMap<K, Counter>,
+    label: impl Fn(&K) -> String,
+    sort: Sort,
+    n: usize,
+) -> Vec<Row> {
+    let mut rows = counters
+        .iter()
+        .map(|(key, counter)| Row {
+            label: label(key),
+            rate: counter.rate,
+            total: counter.total,
+        })
+        .collect::<Vec<_>>();
+
+    rows.sort_by(|a, b| {
+        let by_name = || a.label.cmp(&b.label);
+
+        match sort {
+            Sort::Rate => b.rate.total_cmp(&a.rate).then_with(by_name),
+            Sort::Total => b.total.cmp(&a.total).then_with(by_name),
+            Sort::Name => by_name(),
+        }
+    });
+    rows.truncate(n);
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use win_event_hook::{
+        events::{Event, NamedEvent},
+        handles::WindowHandle,
+        record::EventRecord,
+    };
+
+    use super::{Aggregator, Sort};
+    use crate::fixtures;
+
+    fn record(event: NamedEvent, hwnd: usize, thread: u32) -> EventRecord {
+        EventRecord {
+            hwnd: WindowHandle::from_raw(hwnd),
+            id_event_thread: thread,
+            ..fixtures::record(event)
+        }
+    }
+
+    #[test]
+    fn aggregates_rates_and_totals() {
+        let mut aggregator = Aggregator::new(Some(&[
+            Event::Named(NamedEvent::ObjectShow),
+            Event::Named(NamedEvent::ObjectHide),
+        ]));
+
+        for _ in 0..30 {
+            aggregator.record(&record(NamedEvent::ObjectShow, 0x10, 1));
+        }
+        for _ in 0..10 {
+            aggregator.record(&record(NamedEvent::ObjectLocationChange, 0x20, 2));
+        }
+        aggregator.sample(Duration::from_secs(2));
+
+        let events = aggregator.events(Sort::Rate, 10);
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            (events[0].label.as_str(), events[0].rate),
+            ("ObjectShow", 15.0)
+        );
+        assert_eq!(
+            (events[1].label.as_str(), events[1].rate),
+            ("ObjectLocationChange", 5.0)
+        );
+        // filtered events are listed, even before they're raised
+        assert_eq!(
+            (events[2].label.as_str(), events[2].total),
+            ("ObjectHide", 0)
+        );
+
+        for _ in 0..25 {
+            aggregator.record(&record(NamedEvent::ObjectLocationChange, 0x20, 2));
+        }
+        aggregator.sample(Duration::from_secs(1));
+
+        let events = aggregator.events(Sort::Rate, 1);
+        assert_eq!(
+            (events[0].label.as_str(), events[0].rate, events[0].total),
+            ("ObjectLocationChange", 25.0, 35)
+        );
+
+        let windows = aggregator.windows(Sort::Total, 10);
+        assert_eq!(windows[0].label, "0x00000020");
+        assert_eq!(windows[1].rate, 0.0);
+
+        let threads = aggregator.threads(Sort::Name, 10);
+        assert_eq!(threads[0].label, "1");
+        assert_eq!(threads[0].total, 30);
+    }
+}
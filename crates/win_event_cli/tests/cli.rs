@@ -0,0 +1,126 @@
+use std::process::{Command, Output};
+
+fn win_event_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_win_event_cli"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Unable to run win_event_cli")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "win_event_cli failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn replay_applies_capture_config() {
+    let output = stdout(&win_event_cli(&[
+        "replay",
+        "--fast",
+        "tests/fixtures/sample.bin",
+    ]));
+    let lines = output.lines().collect::<Vec<_>>();
+
+    // ObjectNameChange is outside of the recorded config
+    assert_eq!(lines.len(), 101);
+    assert!(lines[0].starts_with("      TIME  EVENT "));
+    assert!(lines[1].starts_with("  48213050  ObjectLocationChange "));
+    assert!(lines.iter().all(|line| !line.contains("ObjectNameChange")));
+}
+
+#[test]
+fn replay_prints_jsonl() {
+    let output = stdout(&win_event_cli(&[
+        "replay",
+        "--speed",
+        "1000",
+        "--format",
+        "jsonl",
+        "tests/fixtures/sample.jsonl",
+    ]));
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 20);
+    assert!(lines[2].contains(r#""event":"ObjectShow""#));
+    assert!(lines[2].contains(r#""hwnd":"0x00030F24""#));
+}
+
+#[test]
+fn inspect_summarizes_capture() {
+    let output = stdout(&win_event_cli(&[
+        "inspect",
+        "--top",
+        "2",
+        "tests/fixtures/sample.bin",
+    ]));
+
+    assert!(output.contains("host:      FIXTURE-HOST (windows x86_64, win_event_hook 0.4.2)"));
+    assert!(output.contains("records:   120"));
+    assert!(output.contains("span:      15.130s"));
+    assert!(output.contains("  ObjectLocationChange        60  "));
+    assert!(output.contains("  0x000A01B2        72  "));
+    assert!(output.contains("  4120        69  "));
+    assert!(!output.contains("0x00120448"));
+}
+
+#[test]
+fn inspect_reports_damaged_capture() {
+    let output = stdout(&win_event_cli(&["inspect", "tests/fixtures/damaged.bin"]));
+
+    assert!(output.contains("integrity: 69 bytes skipped, 1 resyncs, truncated"));
+    assert!(output.contains("records:   118"));
+}
+
+#[test]
+fn events_decodes_named_event() {
+    let output = stdout(&win_event_cli(&["events", "decode", "0x800C"]));
+
+    assert!(output.contains("category:    named"));
+    assert!(output.contains("event:       ObjectNameChange"));
+    assert!(output.contains("sdk name:    EVENT_OBJECT_NAMECHANGE"));
+}
+
+#[test]
+fn replay_prints_csv_columns() {
+    let output = stdout(&win_event_cli(&[
+        "replay",
+        "--fast",
+        "--format",
+        "csv",
+        "--columns",
+        "id,event,thread",
+        "tests/fixtures/sample.jsonl",
+    ]));
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 21);
+    assert_eq!(lines[0], "id,event,thread");
+    assert!(lines[3].starts_with("0x8002,ObjectShow,"));
+}
+
+#[test]
+fn merge_orders_records_by_time() {
+    let output = stdout(&win_event_cli(&[
+        "merge",
+        "--format",
+        "csv",
+        "--columns",
+        "time,event",
+        "tests/fixtures/sample.bin",
+        "tests/fixtures/sample.jsonl",
+    ]));
+    let times = output
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap().parse::<u32>().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(times.len(), 140);
+    assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+}
@@ -0,0 +1,86 @@
+use std::{
+    hint::black_box,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use win_event_hook::{
+    __private::{simulate_event, SimulatedHook},
+    events::NamedEvent,
+    handles::{OsHandle, WindowHandle},
+};
+
+fn fake_handle(id: usize) -> OsHandle {
+    OsHandle::from_raw(id)
+}
+
+/// Measures dispatch throughput for one hook, while other threads continuously install and uninstall hooks.
+fn dispatch_under_churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch_under_churn");
+    group.throughput(Throughput::Elements(1));
+
+    let hook = SimulatedHook::register(
+        fake_handle(0x1),
+        Box::new(|ev, _, _, _, _, _| {
+            black_box(ev);
+        }),
+        None,
+    );
+
+    for churn_threads in [0, 1, 4] {
+        let running = Arc::new(AtomicBool::new(true));
+
+        let churners = (0..churn_threads)
+            .map(|i| {
+                let running = running.clone();
+
+                thread::spawn(move || {
+                    let handle = fake_handle(0x1000 + i);
+
+                    while running.load(Ordering::Relaxed) {
+                        let churned = SimulatedHook::register(
+                            handle.clone(),
+                            Box::new(|_, _, _, _, _, _| {}),
+                            None,
+                        );
+
+                        drop(black_box(churned));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(churn_threads),
+            &churn_threads,
+            |b, _| {
+                b.iter(|| {
+                    simulate_event(
+                        hook.handle(),
+                        NamedEvent::ObjectShow.into(),
+                        WindowHandle::default(),
+                        0,
+                        0,
+                        0,
+                        0,
+                    )
+                })
+            },
+        );
+
+        running.store(false, Ordering::Relaxed);
+
+        for churner in churners {
+            churner.join().unwrap();
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, dispatch_under_churn);
+criterion_main!(benches);
@@ -0,0 +1,854 @@
+//! A versioned binary format for recording events, so they can be analyzed later, or elsewhere.
+//!
+//! A capture begins with a header, containing:
+//!
+//! - the magic bytes `WEHCAPT\0`
+//! - the format version, as a little-endian `u16`
+//! - the length of the header body, as a little-endian `u32`
+//! - the header body, describing the host, the hook [`Config`] and the time the capture started
+//! - a CRC-32 of the header body, as a little-endian `u32`
+//!
+//! It is followed by any number of records, each containing:
+//!
+//! - the sync marker `WERC`
+//! - the length of the record body, as a little-endian `u16`
+//! - the record body, containing each value passed to the [`EventHandler`], followed by the
+//!   sequence number of the event (as a little-endian `u64`) if it's known
+//! - a CRC-32 of the record body, as a little-endian `u32`
+//!
+//! Note: Readers ignore any record body bytes beyond those they understand, so that values can
+//! be appended to records without breaking existing readers.
+//!
+//! Records that fail validation (for instance, in a partially written file) are skipped by
+//! [`CaptureReader`], which resumes at the next sync marker.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{trace, warn};
+
+use crate::{
+    config::Config,
+    errors::{Error, Result},
+    events::Event,
+    flags::Flags,
+    handler::EventHandler,
+    handles::{ModuleHandle, WindowHandle},
+    record::EventRecord,
+};
+
+/// The magic bytes that begin every capture.
+pub const MAGIC: [u8; 8] = *b"WEHCAPT\0";
+
+/// The version of the capture format written by [`CaptureWriter`].
+pub const VERSION: u16 = 1;
+
+/// The marker that begins every record within a capture.
+const SYNC: [u8; 4] = *b"WERC";
+
+/// The size of a record body, as written by this version of the format, without a sequence number.
+const RECORD_LEN: usize = 28;
+
+/// The size of the optional sequence number, following the values of a record body.
+const SEQUENCE_LEN: usize = 8;
+
+/// The size of the framing around a record body; the sync marker, length and checksum.
+const RECORD_FRAMING_LEN: usize = SYNC.len() + 2 + 4;
+
+/// The largest header body a [`CaptureReader`] will accept.
+const MAX_HEADER_LEN: usize = 1 << 20;
+
+/// Information about the host a capture was recorded on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostMetadata {
+    /// The name of the host, if known.
+    pub hostname: String,
+    /// The operating system of the host, as in [`std::env::consts::OS`].
+    pub os: String,
+    /// The cpu architecture of the host, as in [`std::env::consts::ARCH`].
+    pub arch: String,
+    /// The version of `win_event_hook` that recorded the capture.
+    pub library_version: String,
+}
+
+impl HostMetadata {
+    /// Obtains the [`HostMetadata`] of the current host.
+    pub fn current() -> Self {
+        Self {
+            hostname: std::env::var("COMPUTERNAME")
+                .or_else(|_| std::env::var("HOSTNAME"))
+                .unwrap_or_default(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            library_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// The header of a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureHeader {
+    /// The version of the format the capture was written with.
+    pub version: u16,
+    /// The time at which the capture started.
+    pub start_time: SystemTime,
+    /// The host the capture was recorded on.
+    pub host: HostMetadata,
+    /// The [`Config`] of the hook whose events were recorded.
+    ///
+    /// Note: Values that only exist at runtime, such as a shared hook thread or handoff queues, are not recorded.
+    pub config: Config,
+}
+
+impl CaptureHeader {
+    /// Returns a new [`CaptureHeader`] for a capture starting now, on the current host.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            version: VERSION,
+            start_time: SystemTime::now(),
+            host: HostMetadata::current(),
+            config: Config {
+                #[cfg(windows)]
+                hook_thread: None,
+                handoff: None,
+                sharding: None,
+                ..config.clone()
+            },
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        let start_time = self
+            .start_time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        put_u64(
+            &mut body,
+            start_time.as_nanos().try_into().unwrap_or(u64::MAX),
+        );
+        put_str(&mut body, &self.host.hostname);
+        put_str(&mut body, &self.host.os);
+        put_str(&mut body, &self.host.arch);
+        put_str(&mut body, &self.host.library_version);
+
+        let config = &self.config;
+        put_u32(&mut body, config.event_min);
+        put_u32(&mut body, config.event_max);
+        match &config.event_filter {
+            Some(event_filter) => {
+                body.push(1);
+                put_u32(&mut body, event_filter.len() as u32);
+                for event in event_filter {
+                    put_u32(&mut body, event.into());
+                }
+            }
+            None => body.push(0),
+        }
+        put_u32(&mut body, config.id_process);
+        put_u32(&mut body, config.id_thread);
+        match &config.module_handle {
+            Some(module_handle) => {
+                body.push(1);
+                put_u64(&mut body, module_handle.to_raw() as u64);
+            }
+            None => body.push(0),
+        }
+        put_u32(&mut body, config.dw_flags.bits());
+        match &config.dedicated_thread_name {
+            Some(name) => {
+                body.push(1);
+                put_str(&mut body, name);
+            }
+            None => body.push(0),
+        }
+
+        body
+    }
+
+    fn decode(version: u16, body: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor(body);
+
+        let start_time = UNIX_EPOCH + Duration::from_nanos(cursor.u64()?);
+        let host = HostMetadata {
+            hostname: cursor.str()?,
+            os: cursor.str()?,
+            arch: cursor.str()?,
+            library_version: cursor.str()?,
+        };
+
+        let event_min = cursor.u32()?;
+        let event_max = cursor.u32()?;
+        let event_filter = match cursor.u8()? {
+            0 => None,
+            _ => {
+                let len = cursor.u32()?;
+                let mut event_filter = Vec::new();
+                for _ in 0..len {
+                    event_filter.push(Event::from(cursor.u32()?));
+                }
+                Some(event_filter)
+            }
+        };
+        let id_process = cursor.u32()?;
+        let id_thread = cursor.u32()?;
+        let module_handle = match cursor.u8()? {
+            0 => None,
+            _ => Some(ModuleHandle::from_raw(cursor.u64()? as usize)),
+        };
+        let dw_flags = Flags::from_bits_retain(cursor.u32()?);
+        let dedicated_thread_name = match cursor.u8()? {
+            0 => None,
+            _ => Some(cursor.str()?),
+        };
+
+        Ok(Self {
+            version,
+            start_time,
+            host,
+            config: Config {
+                event_min,
+                event_max,
+                event_filter,
+                id_process,
+                id_thread,
+                module_handle,
+                dw_flags,
+                dedicated_thread_name,
+                #[cfg(windows)]
+                hook_thread: None,
+                handoff: None,
+                sharding: None,
+            },
+        })
+    }
+}
+
+/// Writes events to a capture, as they occur.
+///
+/// Note: Records are written to the underlying writer immediately. Wrap it in a
+/// [`BufWriter`](std::io::BufWriter), and [`flush`](Self::flush) periodically, to reduce the cost of each record.
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    header: CaptureHeader,
+    records_written: u64,
+    failed_writes: u64,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Returns a new [`CaptureWriter`] for the hook with a given [`Config`], writing the capture header.
+    pub fn new(writer: W, config: &Config) -> Result<Self> {
+        Self::with_header(writer, CaptureHeader::new(config))
+    }
+
+    /// Returns a new [`CaptureWriter`], writing a given [`CaptureHeader`].
+    ///
+    /// Note: The header is always written with the current [`VERSION`].
+    pub fn with_header(mut writer: W, header: CaptureHeader) -> Result<Self> {
+        let header = CaptureHeader {
+            version: VERSION,
+            ..header
+        };
+        let body = header.encode();
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(body.len() as u32).to_le_bytes())?;
+        writer.write_all(&body)?;
+        writer.write_all(&crc32(&body).to_le_bytes())?;
+
+        trace!(?header, "wrote capture header");
+
+        Ok(Self {
+            writer,
+            header,
+            records_written: 0,
+            failed_writes: 0,
+        })
+    }
+
+    /// Obtains the header written to the capture.
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Writes a given [`EventRecord`] to the capture.
+    pub fn write(&mut self, record: &EventRecord) -> Result<()> {
+        let body = encode_record(record);
+        let mut frame = Vec::with_capacity(RECORD_FRAMING_LEN + body.len());
+
+        frame.extend_from_slice(&SYNC);
+        frame.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        frame.extend_from_slice(&body);
+        frame.extend_from_slice(&crc32(&body).to_le_bytes());
+
+        // records are written in a single call, to minimize partially written records
+        match self.writer.write_all(&frame) {
+            Ok(()) => {
+                self.records_written += 1;
+
+                Ok(())
+            }
+            Err(err) => {
+                self.failed_writes += 1;
+
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// The number of records successfully written to the capture.
+    pub fn records_written(&self) -> u64 {
+        self.records_written
+    }
+
+    /// The number of records that could not be written to the capture.
+    pub fn failed_writes(&self) -> u64 {
+        self.failed_writes
+    }
+
+    /// Flushes, and returns, the underlying writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Send + 'static> CaptureWriter<W> {
+    /// Returns an [`EventHandler`] that writes each event it receives to a given [`CaptureWriter`].
+    ///
+    /// Note: Failures are logged and counted (see [`Self::failed_writes`]), as they cannot be returned to the hook.
+    pub fn handler(writer: &Arc<Mutex<Self>>) -> impl EventHandler {
+        let writer = writer.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            let mut writer = writer.lock().expect("Unable to obtain capture writer lock");
+
+            if let Err(err) = writer.write(&record) {
+                warn!(?err, ?event, "failed to write event to capture");
+            }
+        }
+    }
+}
+
+/// Reads events from a capture.
+///
+/// Records are obtained by iterating over the reader. Corrupt records are skipped, and reading
+/// resumes at the next valid record; see [`Self::skipped_bytes`] and [`Self::is_truncated`].
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    header: CaptureHeader,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    resyncing: bool,
+    skipped_bytes: u64,
+    resyncs: u64,
+    truncated: bool,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Returns a new [`CaptureReader`], reading the capture header.
+    ///
+    /// Note: Unlike records, a header that fails validation is an error.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut preamble = [0; MAGIC.len() + 2 + 4];
+        read_header_bytes(&mut reader, &mut preamble)?;
+
+        if preamble[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidCapture("missing magic bytes".to_string()));
+        }
+
+        let version = u16::from_le_bytes([preamble[8], preamble[9]]);
+        if version == 0 || version > VERSION {
+            return Err(Error::UnsupportedCaptureVersion(version));
+        }
+
+        let len = u32::from_le_bytes([preamble[10], preamble[11], preamble[12], preamble[13]]);
+        let len = len as usize;
+        if len > MAX_HEADER_LEN {
+            return Err(Error::InvalidCapture(format!(
+                "header too large ({len} bytes)"
+            )));
+        }
+
+        let mut body = vec![0; len + 4];
+        read_header_bytes(&mut reader, &mut body)?;
+
+        let (body, crc) = body.split_at(len);
+        if crc32(body).to_le_bytes() != crc {
+            return Err(Error::InvalidCapture(
+                "header checksum mismatch".to_string(),
+            ));
+        }
+
+        let header = CaptureHeader::decode(version, body)?;
+
+        trace!(?header, "read capture header");
+
+        Ok(Self {
+            reader,
+            header,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            resyncing: false,
+            skipped_bytes: 0,
+            resyncs: 0,
+            truncated: false,
+        })
+    }
+
+    /// Obtains the header of the capture.
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// The number of bytes skipped while searching for valid records.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped_bytes
+    }
+
+    /// The number of times reading resumed after encountering invalid data.
+    pub fn resyncs(&self) -> u64 {
+        self.resyncs
+    }
+
+    /// Determines if the capture ended part way through a record, for instance, if it was still
+    /// being written or the writer was interrupted.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Ensures at least `len` unread bytes are buffered, returning `false` if the capture ends first.
+    fn fill(&mut self, len: usize) -> io::Result<bool> {
+        // discard consumed bytes, once there's enough of them to be worth moving the rest
+        if self.pos > 64 * 1024 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+
+        while self.buf.len() - self.pos < len {
+            if self.eof {
+                return Ok(false);
+            }
+
+            let mut chunk = [0; 8 * 1024];
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.buf.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Skips a given number of bytes, as part of searching for the next valid record.
+    fn skip(&mut self, len: usize) {
+        if !self.resyncing {
+            self.resyncing = true;
+            self.resyncs += 1;
+
+            warn!(offset = self.pos, "invalid capture record, resyncing");
+        }
+
+        self.pos += len;
+        self.skipped_bytes += len as u64;
+    }
+
+    fn next_record(&mut self) -> io::Result<Option<EventRecord>> {
+        loop {
+            if !self.fill(SYNC.len() + 2)? {
+                return Ok(self.finish());
+            }
+
+            let frame = &self.buf[self.pos..];
+            if frame[..SYNC.len()] != SYNC {
+                self.skip(1);
+                continue;
+            }
+
+            let len = u16::from_le_bytes([frame[4], frame[5]]) as usize;
+            if len < RECORD_LEN {
+                self.skip(1);
+                continue;
+            }
+
+            if !self.fill(RECORD_FRAMING_LEN + len)? {
+                // a later sync marker means this length is corrupt, rather than the record truncated, so
+                // resume from it
+                let rest = &self.buf[self.pos + 1..];
+                if let Some(offset) = rest.windows(SYNC.len()).position(|window| window == SYNC) {
+                    self.skip(1 + offset);
+                    continue;
+                }
+
+                return Ok(self.finish());
+            }
+
+            let frame = &self.buf[self.pos..self.pos + RECORD_FRAMING_LEN + len];
+            let body = &frame[SYNC.len() + 2..SYNC.len() + 2 + len];
+            let crc = &frame[SYNC.len() + 2 + len..];
+
+            if crc32(body).to_le_bytes() != crc {
+                self.skip(1);
+                continue;
+            }
+
+            let record = decode_record(body);
+
+            self.pos += frame.len();
+            self.resyncing = false;
+
+            return Ok(Some(record));
+        }
+    }
+
+    /// Handles the end of the capture, noting any trailing bytes that did not form a record.
+    fn finish(&mut self) -> Option<EventRecord> {
+        let remaining = self.buf.len() - self.pos;
+
+        if remaining > 0 {
+            trace!(remaining, "capture ended part way through a record");
+
+            self.truncated = true;
+            self.skipped_bytes += remaining as u64;
+            self.pos = self.buf.len();
+        }
+
+        None
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<EventRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().map_err(Error::from).transpose()
+    }
+}
+
+/// Reads header bytes, reporting a capture that ends early as invalid.
+fn read_header_bytes<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => Error::InvalidCapture("truncated header".to_string()),
+        _ => err.into(),
+    })
+}
+
+fn encode_record(record: &EventRecord) -> Vec<u8> {
+    let mut body = Vec::with_capacity(RECORD_LEN + SEQUENCE_LEN);
+
+    put_u32(&mut body, record.event.into());
+    put_u64(&mut body, record.hwnd.to_raw() as u64);
+    body.extend_from_slice(&record.id_object.to_le_bytes());
+    body.extend_from_slice(&record.id_child.to_le_bytes());
+    put_u32(&mut body, record.id_event_thread);
+    put_u32(&mut body, record.event_time);
+
+    if let Some(sequence) = record.sequence {
+        put_u64(&mut body, sequence);
+    }
+
+    body
+}
+
+/// Decodes a record body, which must be at least [`RECORD_LEN`] bytes.
+fn decode_record(body: &[u8]) -> EventRecord {
+    let mut cursor = Cursor(body);
+    // A failure here indicates a library issue. Please open an issue on GitHub!
+    let expect = "Expected a record body of at least RECORD_LEN bytes";
+
+    let event = Event::from(cursor.u32().expect(expect));
+    let hwnd = WindowHandle::from_raw(cursor.u64().expect(expect) as usize);
+    let id_object = cursor.u32().expect(expect) as i32;
+    let id_child = cursor.u32().expect(expect) as i32;
+    let id_event_thread = cursor.u32().expect(expect);
+    let event_time = cursor.u32().expect(expect);
+
+    EventRecord {
+        event,
+        hwnd,
+        id_object,
+        id_child,
+        id_event_thread,
+        event_time,
+        received: None,
+        // records written without a sequence number end here
+        sequence: cursor.u64().ok(),
+    }
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    let value = &value.as_bytes()[..value.len().min(u16::MAX as usize)];
+
+    buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buf.extend_from_slice(value);
+}
+
+/// Reads little-endian values from the front of a byte slice.
+struct Cursor<'a>(&'a [u8]);
+
+impl Cursor<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.0.len() < N {
+            return Err(Error::InvalidCapture(
+                "unexpected end of header".to_string(),
+            ));
+        }
+
+        let (value, rest) = self.0.split_at(N);
+        self.0 = rest;
+
+        Ok(value.try_into().expect("Expected a slice of N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let len = u16::from_le_bytes(self.take()?) as usize;
+
+        if self.0.len() < len {
+            return Err(Error::InvalidCapture(
+                "unexpected end of header".to_string(),
+            ));
+        }
+
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        String::from_utf8(value.to_vec())
+            .map_err(|_| Error::InvalidCapture("invalid utf-8 in header".to_string()))
+    }
+}
+
+/// Computes the CRC-32 (IEEE) checksum of some bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{crc32, CaptureHeader, CaptureReader, CaptureWriter, HostMetadata, VERSION};
+    use crate::{
+        config::Config,
+        errors::Error,
+        events::{Event, NamedEvent},
+        handles::WindowHandle,
+        record::EventRecord,
+    };
+
+    fn config() -> Config {
+        Config::builder()
+            .with_event(Event::Named(NamedEvent::ObjectShow))
+            .with_event(Event::Named(NamedEvent::ObjectHide))
+            .with_dedicated_thread_name("CaptureThread")
+            .finish()
+    }
+
+    fn record(index: u32) -> EventRecord {
+        EventRecord::new(
+            Event::Named(NamedEvent::ObjectShow),
+            WindowHandle::from_raw(0x1_0000_0000 + index as usize),
+            -4,
+            index as i32,
+            1234,
+            1000 + index,
+        )
+    }
+
+    fn capture(records: u32) -> Vec<u8> {
+        let header = CaptureHeader {
+            version: VERSION,
+            start_time: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            host: HostMetadata {
+                hostname: "host".to_string(),
+                os: "windows".to_string(),
+                arch: "x86_64".to_string(),
+                library_version: "0.0.0".to_string(),
+            },
+            config: config(),
+        };
+        let mut writer = CaptureWriter::with_header(Vec::new(), header).unwrap();
+
+        for index in 0..records {
+            writer.write(&record(index)).unwrap();
+        }
+
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn capture_round_trips() {
+        let bytes = capture(3);
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+
+        assert_eq!(reader.header().config, config());
+        assert_eq!(reader.header().host.hostname, "host");
+        assert_eq!(
+            reader.header().start_time,
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+
+        let records = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records, vec![record(0), record(1), record(2)]);
+        assert_eq!(reader.skipped_bytes(), 0);
+        assert!(!reader.is_truncated());
+    }
+
+    #[test]
+    fn capture_round_trips_sequence() {
+        let sequenced = EventRecord {
+            sequence: Some(41),
+            ..record(1)
+        };
+
+        let mut writer = CaptureWriter::new(Vec::new(), &config()).unwrap();
+        writer.write(&record(0)).unwrap();
+        writer.write(&sequenced).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let records = CaptureReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records, vec![record(0), sequenced]);
+    }
+
+    #[test]
+    fn capture_resyncs_after_corruption() {
+        let mut bytes = capture(3);
+        let record_len = (bytes.len() - capture(0).len()) / 3;
+
+        // corrupt the body of the second record
+        let second = capture(1).len();
+        bytes[second + 10] ^= 0xFF;
+
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        let records = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(records, vec![record(0), record(2)]);
+        assert_eq!(reader.resyncs(), 1);
+        assert_eq!(reader.skipped_bytes(), record_len as u64);
+        assert!(!reader.is_truncated());
+
+        // corrupt the length of the second record, so it appears to run past the end of the capture
+        let mut bytes = capture(3);
+        bytes[second + 4..second + 6].copy_from_slice(&u16::MAX.to_le_bytes());
+
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        let records = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(records, vec![record(0), record(2)]);
+        assert_eq!(reader.resyncs(), 1);
+        assert_eq!(reader.skipped_bytes(), record_len as u64);
+        assert!(!reader.is_truncated());
+    }
+
+    #[test]
+    fn capture_tolerates_truncation() {
+        let mut bytes = capture(2);
+        bytes.truncate(bytes.len() - 5);
+
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        let records = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(records, vec![record(0)]);
+        assert!(reader.is_truncated());
+
+        assert!(matches!(
+            CaptureReader::new(&bytes[..10]),
+            Err(Error::InvalidCapture(_))
+        ));
+    }
+
+    #[test]
+    fn capture_writer_handler_records_events() {
+        let writer = Arc::new(Mutex::new(
+            CaptureWriter::new(Vec::new(), &config()).unwrap(),
+        ));
+        let handler = CaptureWriter::handler(&writer);
+
+        handler(
+            Event::Named(NamedEvent::ObjectShow),
+            WindowHandle::from_raw(0x1_0000_0000),
+            -4,
+            0,
+            1234,
+            1000,
+        );
+        drop(handler);
+
+        let writer = Arc::try_unwrap(writer).ok().unwrap().into_inner().unwrap();
+        assert_eq!(writer.records_written(), 1);
+
+        let bytes = writer.into_inner().unwrap();
+        let records = CaptureReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records, vec![record(0)]);
+    }
+}
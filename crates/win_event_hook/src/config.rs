@@ -1,6 +1,9 @@
 use crate::events::Event;
 use crate::flags::Flags;
 use crate::handles::ModuleHandle;
+use crate::handoff::{Handoff, Overflow, ShardKey, Sharding};
+#[cfg(windows)]
+use crate::hook_thread::HookThread;
 
 /// Config for
 /// [SetWinEventHook](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwineventhook).
@@ -22,6 +25,19 @@ pub struct Config {
     pub dw_flags: Flags,
     /// Specifies the name (and existence) of a thread that will be used for hook management.
     pub dedicated_thread_name: Option<String>,
+    /// Specifies a [`HookThread`], shared with other hooks, that will be used for hook management.
+    ///
+    /// Note: When set, this takes precedence over `dedicated_thread_name`.
+    #[cfg(windows)]
+    pub hook_thread: Option<HookThread>,
+    /// Specifies a bounded queue between the os callback and the hook's handler, which then runs on a worker thread.
+    ///
+    /// Note: When unset, the handler runs within the os callback. See [`crate::handoff`] for more information.
+    pub handoff: Option<Handoff>,
+    /// Specifies several workers that deliver events to the hook's handler in parallel, each through a handoff queue.
+    ///
+    /// Note: When `handoff` is unset, each worker uses the default [`Handoff`].
+    pub sharding: Option<Sharding>,
 }
 
 impl Config {
@@ -45,7 +61,31 @@ impl Config {
             // is specified, this parameter is NULL.
             && ((self.dw_flags.contains(Flags::IN_CONTEXT) && self.module_handle.is_some())
                 || (self.dw_flags.contains(Flags::OUT_OF_CONTEXT) && self.module_handle.is_none()))
+            // Check requirement: a handoff queue can hold at least one event
+            && self.handoff.is_none_or(|handoff| handoff.capacity > 0)
+            // Check requirement: a sharded hook has at least one worker
+            && self.sharding.is_none_or(|sharding| sharding.workers > 0)
     }
+
+    /// Determines if a hook using this config would deliver a given [`Event`], raised by a given thread id.
+    ///
+    /// This considers the event range and thread scope (applied by the os), as well as the
+    /// `event_filter` (applied by this library, as events are dispatched).
+    ///
+    /// Note: Process scope, and the `SKIP_OWN_*` flags, cannot be determined from the event alone,
+    /// so they are not considered.
+    pub fn accepts(&self, event: &Event, id_event_thread: u32) -> bool {
+        let id: u32 = event.into();
+
+        (self.event_min..=self.event_max).contains(&id)
+            && (self.id_thread == 0 || self.id_thread == id_event_thread)
+            && event_filter_accepts(self.event_filter.as_deref(), event)
+    }
+}
+
+/// Determines if a given `event_filter` permits an [`Event`] to be delivered to a hook's handler.
+pub(crate) fn event_filter_accepts(event_filter: Option<&[Event]>, event: &Event) -> bool {
+    event_filter.is_none_or(|event_filter| event_filter.contains(event))
 }
 
 impl Default for Config {
@@ -59,6 +99,10 @@ impl Default for Config {
             module_handle: None,
             dw_flags: Flags::default(),
             dedicated_thread_name: None,
+            #[cfg(windows)]
+            hook_thread: None,
+            handoff: None,
+            sharding: None,
         }
     }
 }
@@ -141,13 +185,14 @@ impl ConfigBuilder {
     ///
     /// Note: Should not be mixed with `with_event`, `with_events` builder methods.
     pub fn with_event_range(self, min: u32, max: u32) -> Self {
-        let event_min = if self.inner.event_min > min {
+        // the default range includes every event, so it's replaced rather than extended
+        let event_min = if self.inner.event_min > min || self.inner.event_min == Event::MIN {
             min
         } else {
             self.inner.event_min
         };
 
-        let event_max = if self.inner.event_max < max {
+        let event_max = if self.inner.event_max < max || self.inner.event_max == Event::MAX {
             max
         } else {
             self.inner.event_max
@@ -167,12 +212,12 @@ impl ConfigBuilder {
     /// Note: This is for advanced use cases; while it's technically supported, you probably don't want this.
     /// To that end, if you're using this method and looking to improve the ergonomics, please open an issue on GitHub!
     pub fn with_module_context(self, module_handle: ModuleHandle) -> Self {
-        // ensure the IN_CONTEXT is removed from the existing flags
+        // ensure the OUT_OF_CONTEXT is removed from the existing flags
         let mut dw_flags = self.inner.dw_flags;
-        dw_flags.remove(Flags::IN_CONTEXT);
+        dw_flags.remove(Flags::OUT_OF_CONTEXT);
 
-        // then add the out of context flag
-        let dw_flags = dw_flags.union(Flags::OUT_OF_CONTEXT);
+        // then add the in context flag, as the module is mapped into the processes raising events
+        let dw_flags = dw_flags.union(Flags::IN_CONTEXT);
 
         Self {
             inner: Config {
@@ -228,6 +273,60 @@ impl ConfigBuilder {
         }
     }
 
+    /// Configures the hook to use a given [`HookThread`], which may be shared with other hooks.
+    ///
+    /// Note: This allows many hooks to install onto, and dispatch from, a single event loop, rather than
+    /// using a dedicated thread for each of them. See [`Self::with_dedicated_thread`] for more information.
+    #[cfg(windows)]
+    pub fn with_hook_thread(self, hook_thread: &HookThread) -> Self {
+        Self {
+            inner: Config {
+                hook_thread: Some(hook_thread.clone()),
+                ..self.inner
+            },
+        }
+    }
+
+    /// Configures the hook to hand events to its handler through a bounded queue, holding a given number of
+    /// events, rather than calling the handler within the os callback.
+    ///
+    /// Note: The handler then runs on a worker thread, so a slow handler doesn't hold up the thread that
+    /// installed the hook. See [`crate::handoff`] for more information.
+    pub fn with_handoff(self, capacity: usize, overflow: Overflow) -> Self {
+        Self {
+            inner: Config {
+                handoff: Some(Handoff { capacity, overflow }),
+                ..self.inner
+            },
+        }
+    }
+
+    /// Configures the hook to deliver events to its handler from a given number of worker threads, in parallel.
+    ///
+    /// Note: Events with the same [`ShardKey`] are delivered by the same worker, in order, so the handler
+    /// must only rely on ordering between those. See [`crate::handoff`] for more information.
+    pub fn with_sharding(self, workers: u32, key: ShardKey) -> Self {
+        Self {
+            inner: Config {
+                sharding: Some(Sharding { workers, key }),
+                ..self.inner
+            },
+        }
+    }
+
+    /// Sets the [`Flags`] of the hook, replacing those set by default (or by previous builder methods).
+    ///
+    /// Note: Later calls to [`Self::with_module_context`], [`Self::skip_own_process`] and
+    /// [`Self::skip_own_thread`] add to these flags.
+    pub fn with_flags(self, dw_flags: Flags) -> Self {
+        Self {
+            inner: Config {
+                dw_flags,
+                ..self.inner
+            },
+        }
+    }
+
     /// Configures the hook to ignore events raised by the current process id.
     pub fn skip_own_process(self) -> Self {
         Self {
@@ -256,3 +355,44 @@ impl ConfigBuilder {
         self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{flags::Flags, handles::ModuleHandle};
+
+    use super::Config;
+
+    #[test]
+    fn module_context_is_in_context() {
+        let config = Config::builder()
+            .with_flags(Flags::OUT_OF_CONTEXT)
+            .skip_own_thread()
+            .with_module_context(ModuleHandle::from_raw(0x1000))
+            .finish();
+
+        assert_eq!(config.dw_flags, Flags::IN_CONTEXT | Flags::SKIP_OWN_THREAD);
+        assert!(config.is_valid());
+
+        let config = Config::builder()
+            .with_flags(Flags::OUT_OF_CONTEXT)
+            .skip_own_thread()
+            .skip_own_process()
+            .finish();
+
+        assert!(!config.is_valid());
+    }
+
+    #[test]
+    fn event_range_narrows_default_range() {
+        let config = Config::builder().with_event_range(0x8000, 0x80FF).finish();
+
+        assert_eq!((config.event_min, config.event_max), (0x8000, 0x80FF));
+
+        let config = Config::builder()
+            .with_event_range(0x8000, 0x80FF)
+            .with_event_range(0x4E00, 0x4EFF)
+            .finish();
+
+        assert_eq!((config.event_min, config.event_max), (0x4E00, 0x80FF));
+    }
+}
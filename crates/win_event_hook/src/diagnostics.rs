@@ -0,0 +1,117 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use arc_swap::ArcSwapOption;
+use tracing::{error, trace};
+
+use crate::{
+    events::Event,
+    handler::OrphanHandler,
+    handles::{OsHandle, WindowHandle},
+};
+
+/// Library-wide diagnostics, describing events that could not be delivered to an installed hook.
+///
+/// See [`diagnostics`] and [`reset_diagnostics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Diagnostics {
+    /// Number of events received for a hook that was not installed. For instance, events buffered by the
+    /// os for a hook that has since been uninstalled.
+    pub unknown_hook_events: u64,
+    /// Number of events received for an installed hook whose handler had already been released.
+    pub released_hook_events: u64,
+}
+
+impl Diagnostics {
+    /// The total number of orphaned events; that is, events that could not be delivered to a hook.
+    pub fn orphaned_events(&self) -> u64 {
+        self.unknown_hook_events + self.released_hook_events
+    }
+}
+
+/// The reason an event could not be delivered to a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Orphan {
+    /// No hook is installed with the event hook handle.
+    UnknownHook,
+    /// A hook is installed with the event hook handle, but its handler has been released.
+    ReleasedHook,
+}
+
+static UNKNOWN_HOOK_EVENTS: AtomicU64 = AtomicU64::new(0);
+static RELEASED_HOOK_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Storage for the fallback handler invoked by [`on_orphaned_event`].
+static ORPHAN_HANDLER: ArcSwapOption<Box<dyn OrphanHandler>> = ArcSwapOption::const_empty();
+
+/// Obtains the current library-wide [`Diagnostics`].
+pub fn diagnostics() -> Diagnostics {
+    Diagnostics {
+        unknown_hook_events: UNKNOWN_HOOK_EVENTS.load(Ordering::Relaxed),
+        released_hook_events: RELEASED_HOOK_EVENTS.load(Ordering::Relaxed),
+    }
+}
+
+/// Resets the library-wide [`Diagnostics`], returning the values prior to the reset.
+pub fn reset_diagnostics() -> Diagnostics {
+    Diagnostics {
+        unknown_hook_events: UNKNOWN_HOOK_EVENTS.swap(0, Ordering::Relaxed),
+        released_hook_events: RELEASED_HOOK_EVENTS.swap(0, Ordering::Relaxed),
+    }
+}
+
+/// Sets a fallback [`OrphanHandler`] function, invoked for each event that could not be delivered to a hook.
+///
+/// Note: This replaces any previously set handler. Panics in the handler are caught and logged.
+pub fn set_orphan_handler<F: OrphanHandler + 'static>(handler: F) {
+    ORPHAN_HANDLER.store(Some(Arc::new(Box::new(handler))));
+}
+
+/// Clears the fallback [`OrphanHandler`] function, if one is set.
+pub fn clear_orphan_handler() {
+    ORPHAN_HANDLER.store(None);
+}
+
+/// Records an event that could not be delivered to a hook, passing it to the [`OrphanHandler`] if one is set.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn on_orphaned_event(
+    orphan: Orphan,
+    event_hook: OsHandle,
+    event: Event,
+    hwnd: WindowHandle,
+    id_object: i32,
+    id_child: i32,
+    id_event_thread: u32,
+    event_time: u32,
+) {
+    match orphan {
+        Orphan::UnknownHook => UNKNOWN_HOOK_EVENTS.fetch_add(1, Ordering::Relaxed),
+        Orphan::ReleasedHook => RELEASED_HOOK_EVENTS.fetch_add(1, Ordering::Relaxed),
+    };
+
+    if let Some(handler) = ORPHAN_HANDLER.load_full() {
+        trace!(?orphan, "invoking orphan handler");
+
+        // panics must not unwind across the os callback, so they're caught instead
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            handler(
+                event_hook,
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            )
+        }));
+
+        if result.is_err() {
+            error!(?orphan, ?event, "orphan handler panicked");
+        }
+    }
+}
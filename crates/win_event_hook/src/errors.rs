@@ -1,7 +1,6 @@
-use rayon::ThreadPoolBuildError;
 use thiserror::Error;
 
-use crate::config::Config;
+use crate::{config::Config, sampling::SamplePolicy};
 
 /// `win_event_hook` library error type.
 #[derive(Error, Debug)]
@@ -9,31 +8,56 @@ pub enum Error {
     /// Indicates an event with a given id is not known.
     #[error("No known event '{0}'")]
     InvalidEvent(u32),
+    /// Indicates an event category with a given name is not known.
+    #[error("No known event category '{0}'")]
+    InvalidEventCategory(String),
     /// Indicates an event with a given id falls outside the configured range.
     #[error("Event '{event}' falls outside valid range [{min}, {max}]")]
     InvalidRangedEvent { event: u32, min: u32, max: u32 },
     /// Indicates a config instance was determined to be invalid.
     #[error("Config '{0:?}' is not valid")]
     InvalidConfig(Config),
+    /// Indicates a sample policy was determined to be invalid.
+    #[error("Sample policy '{0:?}' is not valid")]
+    InvalidSamplePolicy(SamplePolicy),
     /// Indicates an installation failure.
     /// See [Microsoft Documentation](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwineventhook#return-value)
     /// for more information.
     #[error("Failed to install WinEventHook")]
     Installation,
-    /// Indicates an installation failure due to an underlying threadpool issue.
-    #[error("Failed to allocate threadpool")]
-    Threadpool(#[from] ThreadPoolBuildError),
+    /// Indicates an installation failure due to an underlying thread issue.
+    #[error("Failed to spawn hook thread")]
+    Thread(#[source] std::io::Error),
+    /// Indicates an installation failure due to the current platform not supporting event hooks.
+    #[error("WinEventHook is not supported on this platform")]
+    Unsupported,
+    /// Indicates a hook thread was not running, or stopped before it could complete the requested work.
+    #[error("Hook thread is not running")]
+    HookThreadUnavailable,
     /// Indicates an uninstallation failure.
     /// See [Microsoft Documentation](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-unhookwinevent#return-value)
     /// for more information.
     #[error("Failed to uninstall WinEventHook")]
     Uninstallation,
     /// Indicates an uninstallation failure due to an underlying event loop issue.
+    #[cfg(windows)]
     #[error("Failed to terminate eventloop")]
     EventLoop(#[from] windows::core::Error),
     /// Indicates an uninstallation failure due to the hook already being uninstalled.
     #[error("Failed to uninstall WinEventHook, already uninstalled")]
     AlreadyUninstalled,
+    /// Indicates a failure to read or write recorded events.
+    #[error("Failed to read or write recorded events")]
+    Io(#[from] std::io::Error),
+    /// Indicates recorded events were not in the expected format.
+    #[error("Invalid capture: {0}")]
+    InvalidCapture(String),
+    /// Indicates recorded events were written with an unsupported version of the format.
+    #[error("Unsupported capture version '{0}'")]
+    UnsupportedCaptureVersion(u16),
+    /// Indicates a line of JSON Lines did not contain a valid event record.
+    #[error("Invalid event record on line {line}: {reason}")]
+    InvalidJsonRecord { line: usize, reason: String },
 }
 
 /// `win_event_hook` library result type.
@@ -1,14 +1,36 @@
 use tracing::trace;
-use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, MSG, WM_QUIT};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, PeekMessageW, MSG, PM_NOREMOVE, WM_APP, WM_QUIT,
+};
+
+/// Thread message posted to an event loop to indicate that hook thread commands are waiting to be processed.
+pub const WM_HOOK_THREAD_COMMAND: u32 = WM_APP + 1;
+
+/// Ensures the calling thread has a message queue, so that it may receive thread messages
+/// (see [`PostThreadMessageW`](windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW))
+/// before [`run_event_loop`] is started.
+pub unsafe fn ensure_message_queue() {
+    let mut message = MSG::default();
+    let _ = PeekMessageW(&mut message, None, 0, 0, PM_NOREMOVE);
+}
 
 /// Runs a windows event loop for pressing messages using [`GetMessageW`] and [`DispatchMessageW`].
-pub unsafe fn run_event_loop() {
+///
+/// When a [`WM_HOOK_THREAD_COMMAND`] thread message is received, `on_commands` is invoked. If it returns
+/// `false`, the event loop stops.
+pub unsafe fn run_event_loop<F: FnMut() -> bool>(mut on_commands: F) {
     trace!("starting event_loop");
     let mut message = MSG::default();
     while GetMessageW(&mut message, None, 0, 0).into() {
         if message.message == WM_QUIT {
             break;
         }
+        if message.message == WM_HOOK_THREAD_COMMAND && message.hwnd.is_invalid() {
+            if !on_commands() {
+                break;
+            }
+            continue;
+        }
         DispatchMessageW(&message);
     }
     trace!("exiting event_loop");
@@ -1,14 +1,48 @@
+use std::ops::Range;
+
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::*;
+#[cfg(not(windows))]
+use windows_sys::Win32::UI::WindowsAndMessaging::*;
 
 /// A macro that creates a `TryFrom<u32>` implementation for a `repr(u32)` enum.
 /// Adapted from https://stackoverflow.com/a/57578431
+///
+/// Note: Variants must be documented, and assigned a constant, so that the constant name and
+/// documentation are available at runtime.
 macro_rules! u32_to_enum {
     ($(#[$meta:meta])* $vis:vis enum $name:ident {
-        $($(#[$vmeta:meta])* $vname:ident $(= $val:expr)?,)*
+        $($(#[doc = $vdoc:literal])* $vname:ident = $val:ident,)*
     }) => {
         $(#[$meta])*
         $vis enum $name {
-            $($(#[$vmeta])* $vname $(= $val)?,)*
+            $($(#[doc = $vdoc])* $vname = $val,)*
+        }
+
+        impl $name {
+            /// Every variant, in declaration order.
+            pub const ALL: &'static [$name] = &[$($name::$vname,)*];
+
+            /// The name of the variant, for instance `ObjectShow`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$vname => stringify!($vname),)*
+                }
+            }
+
+            /// The name of the constant for the variant, as in the Windows SDK, for instance `EVENT_OBJECT_SHOW`.
+            pub fn sdk_name(self) -> &'static str {
+                match self {
+                    $($name::$vname => stringify!($val),)*
+                }
+            }
+
+            /// The documentation of the variant.
+            pub fn description(self) -> &'static str {
+                match self {
+                    $($name::$vname => concat!($($vdoc),*).trim_start(),)*
+                }
+            }
         }
 
         impl std::convert::TryFrom<u32> for $name {
@@ -43,6 +77,114 @@ impl Event {
 
     /// The highest possible [`Event`] source value ([`u32`]).
     pub const MAX: u32 = EVENT_MAX;
+
+    /// The [`EventCategory`] of the event.
+    pub fn category(&self) -> EventCategory {
+        match self {
+            Event::Named(_) => EventCategory::Named,
+            Event::Aia(_) => EventCategory::Aia,
+            Event::Oem(_) => EventCategory::Oem,
+            Event::Uia(_) => EventCategory::Uia,
+            Event::UiaProperty(_) => EventCategory::UiaProperty,
+            Event::Unknown(_) => EventCategory::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for Event {
+    /// Formats the event by name, for instance `ObjectShow`, or by category and id, for instance `Uia(0x4E00)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Named(named) => f.write_str(named.name()),
+            Event::Aia(_) => write!(f, "Aia({:#06X})", u32::from(self)),
+            Event::Oem(_) => write!(f, "Oem({:#06X})", u32::from(self)),
+            Event::Uia(_) => write!(f, "Uia({:#06X})", u32::from(self)),
+            Event::UiaProperty(_) => write!(f, "UiaProperty({:#06X})", u32::from(self)),
+            Event::Unknown(_) => write!(f, "Unknown({:#06X})", u32::from(self)),
+        }
+    }
+}
+
+/// The categories of [`Event`], matching its variants.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum EventCategory {
+    /// See [`NamedEvent`].
+    Named,
+    /// See [`AiaEvent`].
+    Aia,
+    /// See [`OemEvent`].
+    Oem,
+    /// See [`UiaEvent`].
+    Uia,
+    /// See [`UiaPropertyEvent`].
+    UiaProperty,
+    /// Events that do not fall into any other category.
+    Unknown,
+}
+
+impl EventCategory {
+    /// Every [`EventCategory`], in declaration order.
+    pub const ALL: [EventCategory; 6] = [
+        EventCategory::Named,
+        EventCategory::Aia,
+        EventCategory::Oem,
+        EventCategory::Uia,
+        EventCategory::UiaProperty,
+        EventCategory::Unknown,
+    ];
+
+    /// The range of event ids classified into the category by `Event::from(u32)`, if it has one.
+    ///
+    /// Note: As in each category's `is_within_range`, the range excludes its `MAX` value.
+    /// [`NamedEvent`]s are not allocated as a single range, see [`NamedEvent::ALL`] instead.
+    pub fn range(self) -> Option<Range<u32>> {
+        match self {
+            EventCategory::Aia => Some(AiaEvent::MIN..AiaEvent::MAX),
+            EventCategory::Oem => Some(OemEvent::MIN..OemEvent::MAX),
+            EventCategory::Uia => Some(UiaEvent::MIN..UiaEvent::MAX),
+            EventCategory::UiaProperty => Some(UiaPropertyEvent::MIN..UiaPropertyEvent::MAX),
+            EventCategory::Named | EventCategory::Unknown => None,
+        }
+    }
+
+    /// A short description of the category.
+    pub fn description(self) -> &'static str {
+        match self {
+            EventCategory::Named => "Events defined by the system, with a well-known name.",
+            EventCategory::Aia => {
+                "Events reserved for the Accessibility Interoperability Alliance (AIA)."
+            }
+            EventCategory::Oem => "Events reserved for original equipment manufacturers (OEMs).",
+            EventCategory::Uia => "Events reserved for UI Automation event ids.",
+            EventCategory::UiaProperty => {
+                "Events reserved for UI Automation property-changed event ids."
+            }
+            EventCategory::Unknown => "Events that do not fall into any other category.",
+        }
+    }
+
+    /// A short, lowercase name for the category, for instance `uia_property`.
+    pub fn name(self) -> &'static str {
+        match self {
+            EventCategory::Named => "named",
+            EventCategory::Aia => "aia",
+            EventCategory::Oem => "oem",
+            EventCategory::Uia => "uia",
+            EventCategory::UiaProperty => "uia_property",
+            EventCategory::Unknown => "unknown",
+        }
+    }
+}
+
+impl std::str::FromStr for EventCategory {
+    type Err = crate::errors::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        EventCategory::ALL
+            .into_iter()
+            .find(|category| category.name().eq_ignore_ascii_case(value))
+            .ok_or_else(|| crate::errors::Error::InvalidEventCategory(value.to_string()))
+    }
 }
 
 impl From<NamedEvent> for Event {
@@ -1,7 +1,12 @@
 use bitflags::bitflags;
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
     WINEVENT_INCONTEXT, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS, WINEVENT_SKIPOWNTHREAD,
 };
+#[cfg(not(windows))]
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    WINEVENT_INCONTEXT, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS, WINEVENT_SKIPOWNTHREAD,
+};
 
 bitflags! {
     /// Windows Event Hook flags.
@@ -1,6 +1,76 @@
-use crate::{events::Event, handles::WindowHandle};
+use std::{cell::Cell, time::Instant};
+
+use crate::{
+    events::Event,
+    handles::{OsHandle, WindowHandle},
+    latency::LagAlert,
+    record::EventRecord,
+};
 
 /// Signature of the Event Hook callback function.
 pub trait EventHandler: Fn(Event, WindowHandle, i32, i32, u32, u32) + Sync + Send {}
 
 impl<T> EventHandler for T where T: Fn(Event, WindowHandle, i32, i32, u32, u32) + Sync + Send {}
+
+/// Signature of the fallback callback function, for events that could not be delivered to a hook.
+///
+/// See [`crate::diagnostics::set_orphan_handler`].
+pub trait OrphanHandler:
+    Fn(OsHandle, Event, WindowHandle, i32, i32, u32, u32) + Sync + Send
+{
+}
+
+impl<T> OrphanHandler for T where
+    T: Fn(OsHandle, Event, WindowHandle, i32, i32, u32, u32) + Sync + Send
+{
+}
+
+/// Signature of the function called with each event kept by a sampler, and the number of events it stands in for.
+///
+/// See [`Sampler::handler`](crate::sampling::Sampler::handler).
+pub trait SampledHandler: Fn(&EventRecord, u64) + Sync + Send {}
+
+impl<T> SampledHandler for T where T: Fn(&EventRecord, u64) + Sync + Send {}
+
+/// Signature of the function called when an event is delivered late.
+///
+/// See [`LatencyTracker::with_alert`](crate::latency::LatencyTracker::with_alert).
+pub trait LagAlertHandler: Fn(&LagAlert) + Sync + Send {}
+
+impl<T> LagAlertHandler for T where T: Fn(&LagAlert) + Sync + Send {}
+
+/// How the event being handled was received by its hook.
+///
+/// See [`receipt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Receipt {
+    /// The monotonic time at which the event was received from the os, before it was filtered or delivered.
+    pub received: Instant,
+    /// The position of the event among those delivered by its hook, starting from `0`.
+    ///
+    /// Note: Sequence numbers are assigned after the hook's `event_filter`, so a gap indicates a delivered
+    /// event was lost downstream. See [`GapDetector`](crate::sequence::GapDetector).
+    pub sequence: u64,
+}
+
+thread_local! {
+    /// The [`Receipt`] of the event being handled on this thread, if any.
+    static RECEIPT: Cell<Option<Receipt>> = const { Cell::new(None) };
+}
+
+/// Obtains the [`Receipt`] of the event being handled, when called from an [`EventHandler`].
+///
+/// Note: This is `None` outside of an [`EventHandler`], or when the handler is called directly (for instance by
+/// a [`Replayer`](crate::replay::Replayer)).
+pub fn receipt() -> Option<Receipt> {
+    RECEIPT.get()
+}
+
+/// Runs a given function with a [`Receipt`] available to [`receipt`], restoring the previous one afterwards.
+pub(crate) fn with_receipt<R>(receipt: Receipt, f: impl FnOnce() -> R) -> R {
+    let previous = RECEIPT.replace(Some(receipt));
+    let result = f();
+    RECEIPT.set(previous);
+
+    result
+}
@@ -6,12 +6,40 @@ use std::ops::{Deref, DerefMut};
 pub mod builtins {
     use std::{ffi::c_void, hash::Hash};
 
+    #[cfg(windows)]
     use windows::Win32::{
         Foundation::{HMODULE, HWND},
         UI::Accessibility::HWINEVENTHOOK,
     };
 
-    use super::PlatformHandle;
+    #[cfg(not(windows))]
+    pub use super::portable::{HMODULE, HWINEVENTHOOK, HWND};
+
+    use super::{OpaqueHandle, PlatformHandle};
+
+    /// Implements conversions between the [`OpaqueHandle`] of a built-in handle type and raw addresses.
+    macro_rules! raw_handle {
+        ($name:ident) => {
+            impl OpaqueHandle<$name> {
+                /// Creates a handle from a raw address, such as one previously obtained with [`Self::to_raw`].
+                ///
+                /// Note: This is intended for recorded or simulated handles. The resulting handle is not
+                /// guaranteed to refer to a live os object.
+                pub fn from_raw(raw: usize) -> Self {
+                    Self($name(raw as *mut c_void))
+                }
+
+                /// Obtains the raw address of the handle, suitable for serialization.
+                pub fn to_raw(&self) -> usize {
+                    self.0 .0 as usize
+                }
+            }
+        };
+    }
+
+    raw_handle!(HWINEVENTHOOK);
+    raw_handle!(HMODULE);
+    raw_handle!(HWND);
 
     /// Re-exported [`HWINEVENTHOOK`].
     pub type OsHandle = HWINEVENTHOOK;
@@ -56,6 +84,32 @@ pub mod builtins {
     }
 }
 
+/// Stand-ins for windows handle types, on platforms without the `windows` crate.
+///
+/// Note: These exist so that recorded events (see [`crate::capture`]) can be processed on any platform.
+#[cfg(not(windows))]
+mod portable {
+    use std::ffi::c_void;
+
+    macro_rules! portable_handle {
+        ($name:ident) => {
+            /// A stand-in for the windows handle type of the same name.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct $name(pub *mut c_void);
+
+            impl Default for $name {
+                fn default() -> Self {
+                    Self(std::ptr::null_mut())
+                }
+            }
+        };
+    }
+
+    portable_handle!(HWINEVENTHOOK);
+    portable_handle!(HMODULE);
+    portable_handle!(HWND);
+}
+
 /// A [`Handle`] containing `HWINEVENTHOOK` under-the-hood.
 pub type OsHandle = OpaqueHandle<builtins::OsHandle>;
 
@@ -0,0 +1,351 @@
+//! A bounded queue between the os callback and a hook's handler, so slow handlers don't hold up the os.
+//!
+//! By default, handlers run within the os callback, on the thread that installed the hook. When a
+//! [`Handoff`] is configured (see [`ConfigBuilder::with_handoff`](crate::config::ConfigBuilder::with_handoff)),
+//! the callback instead copies each event into a bounded queue and returns, and the handler runs on a
+//! separate worker thread. When the queue is full, events are handled according to an [`Overflow`] policy,
+//! and each event dropped is counted in [`EventStats::dropped`](crate::stats::EventStats::dropped).
+//!
+//! For handlers that are too slow for a single worker, [`Sharding`] (see
+//! [`ConfigBuilder::with_sharding`](crate::config::ConfigBuilder::with_sharding)) spreads events across
+//! several workers, each with its own queue. Events with the same [`ShardKey`] are always handled by the same
+//! worker, so they're delivered in order, while events with different keys are delivered in parallel.
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{Condvar, Mutex},
+};
+
+use crate::{events::Event, handler::Receipt, handles::WindowHandle};
+
+/// What happens to an event that arrives when a handoff queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The os callback waits for space in the queue.
+    ///
+    /// Note: This holds up the thread that installed the hook, as an inline handler would.
+    Block,
+    /// The arriving event is dropped.
+    DropNewest,
+    /// The oldest queued event is dropped, to make space for the arriving event.
+    DropOldest,
+    /// A queued event with the same event, window, object and child is dropped, and the arriving event
+    /// is queued. Otherwise, the arriving event is dropped.
+    ///
+    /// Note: Events are still delivered in the order they arrived, so sequence numbers stay in order.
+    ///
+    /// Note: This suits events that describe current state, such as `ObjectLocationChange`.
+    Coalesce,
+}
+
+/// Configures a bounded queue between the os callback and a hook's handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handoff {
+    /// The number of events the queue holds, which must be at least `1`.
+    pub capacity: usize,
+    /// What happens to events that arrive when the queue is full.
+    pub overflow: Overflow,
+}
+
+impl Default for Handoff {
+    /// A queue of `1024` events, which blocks when full, so that no events are dropped.
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: Overflow::Block,
+        }
+    }
+}
+
+/// Which events are delivered in order, by the same worker, when a hook is sharded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShardKey {
+    /// Events for the same window.
+    #[default]
+    Window,
+    /// Events raised by the same thread.
+    Thread,
+    /// Events of the same [`Event`] type.
+    Event,
+}
+
+impl ShardKey {
+    /// The index of the shard, out of a given number of shards, that delivers a given event.
+    pub(crate) fn shard(&self, queued: &Queued, shards: usize) -> usize {
+        let mut hasher = DefaultHasher::new();
+
+        match self {
+            ShardKey::Window => queued.hwnd.hash(&mut hasher),
+            ShardKey::Thread => queued.id_event_thread.hash(&mut hasher),
+            ShardKey::Event => queued.event.hash(&mut hasher),
+        }
+
+        (hasher.finish() % shards.max(1) as u64) as usize
+    }
+}
+
+/// Configures several workers, each with a [`Handoff`] queue, to deliver a hook's events in parallel.
+///
+/// Note: The hook's handler is called from several threads at once, and sequence numbers are only in
+/// order for events with the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sharding {
+    /// The number of workers, which must be at least `1`.
+    pub workers: u32,
+    /// Which events are delivered in order, by the same worker.
+    pub key: ShardKey,
+}
+
+/// An event, as it was received by the os callback.
+#[derive(Debug, Clone)]
+pub(crate) struct Queued {
+    pub event: Event,
+    pub hwnd: WindowHandle,
+    pub id_object: i32,
+    pub id_child: i32,
+    pub id_event_thread: u32,
+    pub event_time: u32,
+    pub receipt: Receipt,
+}
+
+impl Queued {
+    /// Determines if two events describe the same thing, so the latter supersedes the former.
+    fn coalesces_with(&self, other: &Queued) -> bool {
+        self.event == other.event
+            && self.hwnd == other.hwnd
+            && self.id_object == other.id_object
+            && self.id_child == other.id_child
+    }
+}
+
+struct State {
+    items: VecDeque<Queued>,
+    closed: bool,
+}
+
+/// A bounded, multi-producer queue of events, drained by a worker.
+pub(crate) struct HandoffQueue {
+    handoff: Handoff,
+    capacity: usize,
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl HandoffQueue {
+    pub fn new(handoff: Handoff) -> Self {
+        // a queue that can't hold an event would block forever, so it holds at least one
+        let capacity = handoff.capacity.max(1);
+
+        Self {
+            handoff,
+            capacity,
+            state: Mutex::new(State {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    /// Adds an event to the queue, returning the event that was dropped to make space, if any.
+    ///
+    /// Note: Events pushed after the queue is closed are dropped.
+    pub fn push(&self, item: Queued) -> Option<Queued> {
+        let mut state = self.lock();
+
+        if state.closed {
+            return Some(item);
+        }
+
+        if state.items.len() >= self.capacity {
+            match self.handoff.overflow {
+                Overflow::Block => {
+                    state = self
+                        .not_full
+                        .wait_while(state, |state| {
+                            !state.closed && state.items.len() >= self.capacity
+                        })
+                        // A failure here indicates a library issue. Please open an issue on GitHub!
+                        .expect("Unable to obtain handoff queue lock");
+
+                    if state.closed {
+                        return Some(item);
+                    }
+                }
+                Overflow::DropNewest => return Some(item),
+                Overflow::DropOldest => {
+                    let dropped = state.items.pop_front();
+                    state.items.push_back(item);
+
+                    return dropped;
+                }
+                Overflow::Coalesce => {
+                    let Some(index) = state
+                        .items
+                        .iter()
+                        .position(|queued| queued.coalesces_with(&item))
+                    else {
+                        return Some(item);
+                    };
+
+                    let dropped = state.items.remove(index);
+                    state.items.push_back(item);
+
+                    return dropped;
+                }
+            }
+        }
+
+        state.items.push_back(item);
+        self.not_empty.notify_one();
+
+        None
+    }
+
+    /// Removes the oldest event from the queue, waiting for one if it's empty.
+    ///
+    /// Note: Once the queue is closed, the remaining events are returned, then `None`.
+    pub fn pop(&self) -> Option<Queued> {
+        let mut state = self
+            .not_empty
+            .wait_while(self.lock(), |state| !state.closed && state.items.is_empty())
+            // A failure here indicates a library issue. Please open an issue on GitHub!
+            .expect("Unable to obtain handoff queue lock");
+
+        let item = state.items.pop_front();
+        self.not_full.notify_one();
+
+        item
+    }
+
+    /// Closes the queue, so that workers stop once it's drained.
+    pub fn close(&self) {
+        self.lock().closed = true;
+
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // A failure here indicates a library issue. Please open an issue on GitHub!
+        self.state
+            .lock()
+            .expect("Unable to obtain handoff queue lock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Instant};
+
+    use super::{Handoff, HandoffQueue, Overflow, Queued, ShardKey};
+    use crate::{
+        events::{Event, NamedEvent},
+        handler::Receipt,
+        handles::WindowHandle,
+    };
+
+    fn queued(event: NamedEvent, hwnd: usize, sequence: u64) -> Queued {
+        Queued {
+            event: Event::Named(event),
+            hwnd: WindowHandle::from_raw(hwnd),
+            id_object: 0,
+            id_child: 0,
+            id_event_thread: 1,
+            event_time: 0,
+            receipt: Receipt {
+                received: Instant::now(),
+                sequence,
+            },
+        }
+    }
+
+    fn sequences(queue: &HandoffQueue) -> Vec<u64> {
+        queue.close();
+
+        std::iter::from_fn(|| queue.pop())
+            .map(|item| item.receipt.sequence)
+            .collect()
+    }
+
+    fn fill(overflow: Overflow) -> HandoffQueue {
+        let queue = HandoffQueue::new(Handoff {
+            capacity: 2,
+            overflow,
+        });
+
+        assert!(queue
+            .push(queued(NamedEvent::ObjectLocationChange, 1, 0))
+            .is_none());
+        assert!(queue
+            .push(queued(NamedEvent::ObjectLocationChange, 2, 1))
+            .is_none());
+
+        queue
+    }
+
+    #[test]
+    fn overflow_drops_by_policy() {
+        let queue = fill(Overflow::DropNewest);
+        let dropped = queue.push(queued(NamedEvent::ObjectShow, 1, 2));
+        assert_eq!(dropped.map(|item| item.receipt.sequence), Some(2));
+        assert_eq!(sequences(&queue), vec![0, 1]);
+
+        let queue = fill(Overflow::DropOldest);
+        let dropped = queue.push(queued(NamedEvent::ObjectShow, 1, 2));
+        assert_eq!(dropped.map(|item| item.receipt.sequence), Some(0));
+        assert_eq!(sequences(&queue), vec![1, 2]);
+
+        let queue = fill(Overflow::Coalesce);
+        let dropped = queue.push(queued(NamedEvent::ObjectLocationChange, 1, 2));
+        assert_eq!(dropped.map(|item| item.receipt.sequence), Some(0));
+        let dropped = queue.push(queued(NamedEvent::ObjectShow, 1, 3));
+        assert_eq!(dropped.map(|item| item.receipt.sequence), Some(3));
+        assert_eq!(sequences(&queue), vec![1, 2]);
+    }
+
+    #[test]
+    fn overflow_blocks_until_space() {
+        let queue = Arc::new(fill(Overflow::Block));
+
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(queued(NamedEvent::ObjectShow, 1, 2)))
+        };
+
+        assert_eq!(queue.pop().map(|item| item.receipt.sequence), Some(0));
+        assert!(producer.join().unwrap().is_none());
+        assert_eq!(sequences(&queue), vec![1, 2]);
+    }
+
+    #[test]
+    fn shard_key_selects_fields() {
+        let first = queued(NamedEvent::ObjectShow, 1, 0);
+        let second = Queued {
+            id_event_thread: 2,
+            ..queued(NamedEvent::ObjectHide, 1, 1)
+        };
+
+        // shards are stable, and in range
+        for key in [ShardKey::Window, ShardKey::Thread, ShardKey::Event] {
+            assert_eq!(key.shard(&first, 8), key.shard(&first, 8));
+            assert!(key.shard(&first, 8) < 8);
+        }
+
+        assert_eq!(
+            ShardKey::Window.shard(&first, 1024),
+            ShardKey::Window.shard(&second, 1024)
+        );
+        assert_ne!(
+            (0..64)
+                .map(|hwnd| ShardKey::Window.shard(&queued(NamedEvent::ObjectShow, hwnd, 0), 4))
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+            1
+        );
+        assert_eq!(ShardKey::Thread.shard(&second, 1), 0);
+    }
+}
@@ -1,43 +1,84 @@
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock, Weak},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
 };
 
 use lazy_static::lazy_static;
-use rayon::{ThreadPool, ThreadPoolBuilder};
-use tracing::{debug, trace, warn};
-use windows::Win32::{
-    Foundation::{LPARAM, WPARAM},
-    System::Threading::GetCurrentThreadId,
-    UI::{
-        Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
-        WindowsAndMessaging::{PostThreadMessageW, WM_QUIT},
-    },
-};
+use tracing::{debug, error, trace};
+#[cfg(windows)]
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
 
 use crate::{
-    config::Config,
+    config::{event_filter_accepts, Config},
+    diagnostics::{on_orphaned_event, Orphan},
     errors::{Error, Result},
-    event_loop::run_event_loop,
     events::Event,
-    handler::EventHandler,
-    handles::{self, OsHandle, WindowHandle},
+    handler::{with_receipt, EventHandler, Receipt},
+    handles::{OsHandle, WindowHandle},
+    handoff::{Handoff, HandoffQueue, Queued, ShardKey, Sharding},
+    registry::Registry,
+    stats::{HookStats, Stats},
 };
+#[cfg(windows)]
+use crate::{handles, hook_thread::HookThread};
 
 pub trait WinEventHookInner: Sync + Send {
     fn handle(&self) -> &Option<OsHandle>;
     fn installed(&self) -> bool;
     fn uninstall(&mut self) -> Result<()>;
+    fn stats(&self) -> &HookStats;
+}
+
+/// Installs a hook with the os, using a given [`Config`] and [`EventHandler`].
+#[cfg(windows)]
+pub fn install(
+    config: Config,
+    handler: Box<dyn EventHandler>,
+) -> Result<Box<dyn WinEventHookInner>> {
+    Ok(
+        match config.hook_thread.is_none() && config.dedicated_thread_name.is_none() {
+            true => Box::new(UnthreadedInner::new(config, handler)?),
+            false => Box::new(ThreadedInner::new(config, handler)?),
+        },
+    )
+}
+
+/// Installs a hook with the os, using a given [`Config`] and [`EventHandler`].
+///
+/// Note: Hooks can only be installed on windows. Elsewhere, this always fails with [`Error::Unsupported`].
+#[cfg(not(windows))]
+pub fn install(
+    config: Config,
+    _handler: Box<dyn EventHandler>,
+) -> Result<Box<dyn WinEventHookInner>> {
+    trace!(?config, "unable to install hook on this platform");
+
+    Err(Error::Unsupported)
 }
 
+#[cfg(windows)]
 pub struct UnthreadedInner {
     handle: Option<OsHandle>,
     _config: Config,
-    _handler: Arc<EventData>,
+    event_data: Arc<EventData>,
 }
 
+#[cfg(windows)]
 impl UnthreadedInner {
     pub fn new(config: Config, handler: Box<dyn EventHandler>) -> Result<Self> {
+        // handoff workers are started first, so a failure doesn't leave a hook installed
+        let event_data = EventData::new(
+            handler,
+            config.event_filter.clone(),
+            config.handoff,
+            config.sharding,
+        )?;
+
         let module_handle = config.module_handle.clone().unwrap_or_default();
         let handle = unsafe {
             SetWinEventHook(
@@ -55,28 +96,19 @@ impl UnthreadedInner {
 
         trace!(?handle, "installed hook");
 
-        let handler = Arc::new((handler, config.event_filter.clone()));
-
-        // block-scoped write-lock for INSTALLED_HOOKS
-        {
-            // A failure here indicates a library issue. Please open an issue on GitHub!
-            let mut hooks = INSTALLED_HOOKS
-                .write()
-                .expect("Unable to obtain write lock");
-
-            hooks.insert(handle.clone(), Arc::downgrade(&handler));
-        }
+        INSTALLED_HOOKS.insert(handle.clone(), &event_data);
 
         trace!("write hook weakref into storage");
 
         Ok(Self {
             handle: Some(handle),
             _config: config,
-            _handler: handler,
+            event_data,
         })
     }
 }
 
+#[cfg(windows)]
 impl WinEventHookInner for UnthreadedInner {
     fn handle(&self) -> &Option<OsHandle> {
         &self.handle
@@ -86,30 +118,41 @@ impl WinEventHookInner for UnthreadedInner {
         self.handle.is_some()
     }
 
+    fn stats(&self) -> &HookStats {
+        &self.event_data.stats
+    }
+
     fn uninstall(&mut self) -> Result<()> {
         if let Some(handle) = self.handle.take() {
-            // A failure here indicates a library issue. Please open an issue on GitHub!
-            let mut hooks = INSTALLED_HOOKS
-                .write()
-                .expect("Unable to obtain write lock");
-
-            let status = unsafe { UnhookWinEvent(*handle) };
-            match status.as_bool() {
-                true => {
-                    hooks.remove(&handle);
+            let result = uninstall_handle(handle);
 
-                    trace!(?handle, "uninstalled hook");
+            // events already queued are delivered before this returns
+            self.event_data.shutdown();
 
-                    Ok(())
-                }
-                false => Err(Error::Uninstallation),
-            }
+            result
         } else {
             Err(Error::AlreadyUninstalled)
         }
     }
 }
 
+#[cfg(windows)]
+/// Uninstalls the hook with a given [`OsHandle`], removing it from [`INSTALLED_HOOKS`].
+pub fn uninstall_handle(handle: OsHandle) -> Result<()> {
+    let status = unsafe { UnhookWinEvent(*handle) };
+    match status.as_bool() {
+        true => {
+            INSTALLED_HOOKS.remove(&handle);
+
+            trace!(?handle, "uninstalled hook");
+
+            Ok(())
+        }
+        false => Err(Error::Uninstallation),
+    }
+}
+
+#[cfg(windows)]
 impl Drop for UnthreadedInner {
     fn drop(&mut self) {
         if self.installed() {
@@ -118,63 +161,37 @@ impl Drop for UnthreadedInner {
     }
 }
 
+#[cfg(windows)]
 pub struct ThreadedInner {
     unthreaded: UnthreadedInner,
-    thread_pool: Arc<ThreadPool>,
-    thread_pool_tid: u32,
+    thread: HookThread,
 }
 
+#[cfg(windows)]
 impl ThreadedInner {
     pub fn new(config: Config, handler: Box<dyn EventHandler>) -> Result<Self> {
-        let thread_name = config
-            .dedicated_thread_name
-            .clone()
-            // A failure here indicates a library issue. Please open an issue on GitHub!
-            .expect("Expected a dedicated_thread_name when allocating ThreadedInner");
-
-        let thread_pool = Arc::new(
-            ThreadPoolBuilder::new()
-                .thread_name(move |i| format!("{thread_name}{i}"))
-                .num_threads(1)
-                .build()?,
-        );
-
-        trace!(?thread_pool, "created thread_pool");
-
-        // obtain the thread pool thread_id
-        let thread_pool_tid = thread_pool.install(|| unsafe { GetCurrentThreadId() });
-
-        // create a forwarding handler that invokes on the thread_pool
-        let captured_thread_pool = thread_pool.clone();
-        let threaded_handler = Box::new(
-            move |e: Event, h: WindowHandle, obj: i32, child: i32, thread: u32, time: u32| {
-                captured_thread_pool.install(|| {
-                    let callback = handler.as_ref();
-
-                    callback(e, h, obj, child, thread, time);
-                });
-            },
-        );
-
-        // ensure the actual hook is installed within the thread_pool
-        let unthreaded = thread_pool.install(|| UnthreadedInner::new(config, threaded_handler))?;
-
-        trace!("created UnthreadedInner child for ThreadedInner");
+        let thread = match &config.hook_thread {
+            Some(thread) => thread.clone(),
+            None => HookThread::with_name(
+                config
+                    .dedicated_thread_name
+                    .as_deref()
+                    // A failure here indicates a library issue. Please open an issue on GitHub!
+                    .expect("Expected a dedicated_thread_name when allocating ThreadedInner"),
+            ),
+        };
 
-        thread_pool.spawn(|| unsafe {
-            run_event_loop();
-        });
+        // ensure the actual hook is installed within the hook thread, as its events are
+        // delivered by the event loop of the installing thread
+        let unthreaded = thread.install(config, handler)?;
 
-        trace!("spawned event_loop on thread_pool");
+        trace!(?thread, "created UnthreadedInner child for ThreadedInner");
 
-        Ok(Self {
-            unthreaded,
-            thread_pool,
-            thread_pool_tid,
-        })
+        Ok(Self { unthreaded, thread })
     }
 }
 
+#[cfg(windows)]
 impl WinEventHookInner for ThreadedInner {
     fn handle(&self) -> &Option<OsHandle> {
         &self.unthreaded.handle
@@ -184,20 +201,26 @@ impl WinEventHookInner for ThreadedInner {
         self.unthreaded.installed()
     }
 
+    fn stats(&self) -> &HookStats {
+        self.unthreaded.stats()
+    }
+
     fn uninstall(&mut self) -> Result<()> {
-        if self.installed() {
-            // stop the event loop
-            unsafe { PostThreadMessageW(self.thread_pool_tid, WM_QUIT, WPARAM(0), LPARAM(0)) }
-                .map_err(|_| Error::Uninstallation)?;
+        if let Some(handle) = self.unthreaded.handle.take() {
+            // uninstall the event hook on the thread that installed it, stopping the
+            // thread if this was the last hook using it
+            let result = self.thread.uninstall(handle);
+
+            self.unthreaded.event_data.shutdown();
 
-            // uninstall the event hook, and return the result
-            self.thread_pool.install(|| self.unthreaded.uninstall())
+            result
         } else {
             Err(Error::AlreadyUninstalled)
         }
     }
 }
 
+#[cfg(windows)]
 impl Drop for ThreadedInner {
     fn drop(&mut self) {
         if self.installed() {
@@ -206,15 +229,118 @@ impl Drop for ThreadedInner {
     }
 }
 
-/// This represents the content of the [`Weak`] within [`INSTALLED_HOOKS`].
-type EventData = (Box<dyn EventHandler>, Option<Vec<Event>>);
+/// This represents the content of the weakref within [`INSTALLED_HOOKS`].
+struct EventData {
+    handler: Box<dyn EventHandler>,
+    event_filter: Option<Vec<Event>>,
+    stats: HookStats,
+    /// The sequence number of the next event delivered to the handler.
+    sequence: AtomicU64,
+    /// The workers delivering events to the handler, or none if it's called within the os callback.
+    workers: Vec<HandoffWorker>,
+    shard_key: ShardKey,
+}
+
+/// A [`HandoffQueue`], and the worker thread delivering its events to the handler.
+struct HandoffWorker {
+    queue: HandoffQueue,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventData {
+    fn new(
+        handler: Box<dyn EventHandler>,
+        event_filter: Option<Vec<Event>>,
+        handoff: Option<Handoff>,
+        sharding: Option<Sharding>,
+    ) -> Result<Arc<Self>> {
+        let workers = match (handoff, sharding) {
+            (_, Some(sharding)) => sharding.workers.max(1) as usize,
+            (Some(_), None) => 1,
+            (None, None) => 0,
+        };
+        let handoff = handoff.unwrap_or_default();
+
+        let event_data = Arc::new(Self {
+            handler,
+            event_filter,
+            stats: HookStats::new(),
+            sequence: AtomicU64::new(0),
+            workers: (0..workers)
+                .map(|_| HandoffWorker {
+                    queue: HandoffQueue::new(handoff),
+                    thread: Mutex::new(None),
+                })
+                .collect(),
+            shard_key: sharding.map(|sharding| sharding.key).unwrap_or_default(),
+        });
+
+        for (index, worker) in event_data.workers.iter().enumerate() {
+            // each worker holds a strong ref until its queue is closed, see `shutdown`
+            let worker_data = event_data.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("WinEventHookWorker{index}"))
+                .spawn(move || {
+                    while let Some(queued) = worker_data.workers[index].queue.pop() {
+                        deliver(&worker_data, queued);
+                    }
+
+                    trace!(index, "handoff worker stopped");
+                });
+
+            let thread = match spawned {
+                Ok(thread) => thread,
+                Err(err) => {
+                    // stops the workers already started, which hold strong refs
+                    event_data.shutdown();
+
+                    return Err(Error::Thread(err));
+                }
+            };
+
+            *worker
+                .thread
+                .lock()
+                .expect("Unable to obtain handoff worker lock") = Some(thread);
+        }
+
+        Ok(event_data)
+    }
+
+    /// Closes the handoff queues, if any, waiting for the workers to deliver the events already queued.
+    ///
+    /// Note: Events that arrive afterwards are counted as dropped.
+    fn shutdown(&self) {
+        // every queue is closed first, so the workers drain in parallel
+        for worker in &self.workers {
+            worker.queue.close();
+        }
+
+        for worker in &self.workers {
+            let thread = worker
+                .thread
+                .lock()
+                .expect("Unable to obtain handoff worker lock")
+                .take();
+
+            // a handler that uninstalls its own hook can't wait for itself to finish
+            if let Some(thread) =
+                thread.filter(|thread| thread.thread().id() != thread::current().id())
+            {
+                if thread.join().is_err() {
+                    error!("handoff worker panicked");
+                }
+            }
+        }
+    }
+}
 
 lazy_static! {
     /// Storage for hooks that need to be invoked by `__on_win_event_hook_event`.
-    static ref INSTALLED_HOOKS: RwLock<HashMap<OsHandle, Weak<EventData>>> =
-        RwLock::new(HashMap::new());
+    static ref INSTALLED_HOOKS: Registry<EventData> = Registry::new();
 }
 
+#[cfg(windows)]
 /// System-exposed springboard for raising `win_event_hook` [`EventHandler`] callbacks.
 extern "system" fn __on_win_event_hook_event(
     event_hook: HWINEVENTHOOK,
@@ -225,12 +351,38 @@ extern "system" fn __on_win_event_hook_event(
     id_event_thread: u32,
     event_time: u32,
 ) {
-    // A failure here indicates a library bug! Please open an issue on GitHub!
+    // taken first, so that it reflects when the os delivered the event
+    let received = Instant::now();
+
+    dispatch(
+        event_hook.into(),
+        event,
+        hwnd.into(),
+        id_object,
+        id_child,
+        id_event_thread,
+        event_time,
+        received,
+    );
+}
+
+/// Raises the [`EventHandler`] of the hook installed with a given [`OsHandle`].
+///
+/// Events that cannot be matched to a hook are recorded as orphaned, see [`crate::diagnostics`].
+#[allow(clippy::too_many_arguments)]
+fn dispatch(
+    event_hook: OsHandle,
+    event: u32,
+    hwnd: WindowHandle,
+    id_object: i32,
+    id_child: i32,
+    id_event_thread: u32,
+    event_time: u32,
+    received: Instant,
+) {
     let event = Event::from(event);
-    let hooks = INSTALLED_HOOKS.read().expect("Unable to obtain read lock");
-    let event_data = hooks
-        .get(&event_hook.into())
-        .unwrap_or_else(|| panic!("Unable to obtain hook: {:?}", event_hook));
+
+    let event_data = INSTALLED_HOOKS.get(&event_hook);
 
     debug!(
         ?event_hook,
@@ -243,44 +395,622 @@ extern "system" fn __on_win_event_hook_event(
         "got event"
     );
 
-    if let Some(event_data) = event_data.upgrade() {
-        trace!("got ref to event_data");
+    let orphan = match event_data.map(|event_data| event_data.upgrade()) {
+        Some(Some(event_data)) => {
+            trace!("got ref to event_data");
 
-        let event_handler = &event_data.0;
-        let event_filter = &event_data.1;
+            let counters = event_data.stats.counters(event);
+            counters.on_received();
 
-        trace!(?event_filter, "filter");
+            let event_filter = &event_data.event_filter;
+
+            trace!(?event_filter, "filter");
 
-        match event_filter {
             // if we have an event filter only call the handler
             // if the given filter contains our event
-            Some(event_filter) => {
-                if event_filter.contains(&event) {
-                    event_handler(
-                        event,
-                        hwnd.into(),
+            if !event_filter_accepts(event_filter.as_deref(), &event) {
+                counters.on_filtered();
+
+                return;
+            }
+
+            // events are numbered before the handoff queues, so those they drop appear as gaps
+            let queued = Queued {
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+                receipt: Receipt {
+                    received,
+                    sequence: event_data.sequence.fetch_add(1, Ordering::Relaxed),
+                },
+            };
+
+            match event_data.workers.len() {
+                0 => deliver(&event_data, queued),
+                workers => {
+                    let shard = event_data.shard_key.shard(&queued, workers);
+
+                    if let Some(dropped) = event_data.workers[shard].queue.push(queued) {
+                        trace!(event = ?dropped.event, shard, "handoff queue dropped event");
+
+                        event_data.stats.counters(dropped.event).on_dropped();
+                    }
+                }
+            }
+
+            return;
+        }
+        Some(None) => {
+            // orphans are counted by `diagnostics`, so they aren't logged above debug on the os callback
+            debug!("Unable to find event handler with id: '{:?}'", event_hook.0);
+
+            Orphan::ReleasedHook
+        }
+        None => {
+            // it's possible for this to occur for os buffered events after we've uninstalled,
+            // or for in-context events raised before we've finished installing.
+            // As a result, this is recorded as an orphan rather than a panic.
+            debug!("Unable to find hook with id: '{:?}'", event_hook.0);
+
+            Orphan::UnknownHook
+        }
+    };
+
+    on_orphaned_event(
+        orphan,
+        event_hook,
+        event,
+        hwnd,
+        id_object,
+        id_child,
+        id_event_thread,
+        event_time,
+    );
+}
+
+/// Raises the [`EventHandler`] of a hook for a given event, recording its [`Receipt`] and stats.
+fn deliver(event_data: &EventData, queued: Queued) {
+    let Queued {
+        event,
+        hwnd,
+        id_object,
+        id_child,
+        id_event_thread,
+        event_time,
+        receipt,
+    } = queued;
+
+    // panics must not unwind across the os callback, so they're caught and counted instead
+    let started = Instant::now();
+    let result = with_receipt(receipt, || {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            (event_data.handler)(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            )
+        }))
+    });
+    event_data
+        .stats
+        .counters(event)
+        .on_delivered(started.elapsed(), result.is_err());
+
+    if result.is_err() {
+        error!(?event, "event handler panicked");
+    }
+}
+
+/// A hook that is registered for dispatch, without being installed with the os.
+///
+/// Note: This supports simulated dispatch in tests and benchmarks, and is not part of the public API.
+#[doc(hidden)]
+pub struct SimulatedHook {
+    handle: OsHandle,
+    event_data: Arc<EventData>,
+}
+
+impl SimulatedHook {
+    /// Registers a given [`EventHandler`] for dispatch, with a given [`OsHandle`].
+    pub fn register(
+        handle: OsHandle,
+        handler: Box<dyn EventHandler>,
+        event_filter: Option<Vec<Event>>,
+    ) -> Self {
+        Self::register_with_handoff(handle, handler, event_filter, None, None)
+            // A failure here indicates a library issue. Please open an issue on GitHub!
+            .expect("Unable to register hook without handoff workers")
+    }
+
+    /// Registers a given [`EventHandler`] for dispatch, with a given [`OsHandle`], through optional handoff
+    /// queues, see [`crate::handoff`].
+    pub fn register_with_handoff(
+        handle: OsHandle,
+        handler: Box<dyn EventHandler>,
+        event_filter: Option<Vec<Event>>,
+        handoff: Option<Handoff>,
+        sharding: Option<Sharding>,
+    ) -> Result<Self> {
+        let event_data = EventData::new(handler, event_filter, handoff, sharding)?;
+
+        INSTALLED_HOOKS.insert(handle.clone(), &event_data);
+
+        Ok(Self { handle, event_data })
+    }
+
+    /// Obtains a reference to the handle the hook is registered with.
+    pub fn handle(&self) -> &OsHandle {
+        &self.handle
+    }
+
+    /// Obtains the runtime [`Stats`] of the hook.
+    pub fn stats(&self) -> Stats {
+        self.event_data.stats.snapshot()
+    }
+}
+
+impl Drop for SimulatedHook {
+    fn drop(&mut self) {
+        INSTALLED_HOOKS.remove(&self.handle);
+        self.event_data.shutdown();
+    }
+}
+
+/// Dispatches an event as though it was raised by the os, for the hook with a given [`OsHandle`].
+///
+/// Note: This supports simulated dispatch in tests and benchmarks, and is not part of the public API.
+#[doc(hidden)]
+pub fn simulate_event(
+    event_hook: &OsHandle,
+    event: u32,
+    hwnd: WindowHandle,
+    id_object: i32,
+    id_child: i32,
+    id_event_thread: u32,
+    event_time: u32,
+) {
+    dispatch(
+        event_hook.clone(),
+        event,
+        hwnd,
+        id_object,
+        id_child,
+        id_event_thread,
+        event_time,
+        Instant::now(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        thread,
+        time::Instant,
+    };
+
+    use super::{dispatch, EventData, SimulatedHook, INSTALLED_HOOKS};
+    use crate::{
+        diagnostics::{clear_orphan_handler, diagnostics, reset_diagnostics, set_orphan_handler},
+        events::{Event, NamedEvent},
+        handler::{receipt, Receipt},
+        handles::{OsHandle, WindowHandle},
+        handoff::{Handoff, Overflow, Queued, ShardKey, Sharding},
+        record::EventRecord,
+        sequence::GapDetector,
+    };
+
+    /// Serializes tests that observe the library-wide diagnostics.
+    static DIAGNOSTICS_LOCK: Mutex<()> = Mutex::new(());
+
+    fn fake_handle(id: usize) -> OsHandle {
+        OsHandle::from_raw(id)
+    }
+
+    fn simulate_event(event_hook: &OsHandle, event: NamedEvent) {
+        dispatch(
+            event_hook.clone(),
+            event.into(),
+            WindowHandle::default(),
+            0,
+            0,
+            0,
+            0,
+            Instant::now(),
+        );
+    }
+
+    fn counting_event_data(delivered: &Arc<AtomicU64>) -> Arc<EventData> {
+        let delivered = delivered.clone();
+
+        EventData::new(
+            Box::new(move |_, _, _, _, _, _| {
+                delivered.fetch_add(1, Ordering::SeqCst);
+            }),
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn unknown_hook_is_orphaned() {
+        let _lock = DIAGNOSTICS_LOCK.lock().unwrap();
+        let event_hook = fake_handle(0x2801);
+        let orphaned = Arc::new(AtomicU64::new(0));
+
+        reset_diagnostics();
+
+        let captured_orphaned = orphaned.clone();
+        let captured_event_hook = event_hook.clone();
+        set_orphan_handler(move |hook, ev, _, _, _, _, _| {
+            assert_eq!(hook, captured_event_hook);
+            assert_eq!(ev, Event::Named(NamedEvent::ObjectShow));
+
+            captured_orphaned.fetch_add(1, Ordering::SeqCst);
+        });
+
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+
+        clear_orphan_handler();
+
+        assert_eq!(diagnostics().unknown_hook_events, 1);
+        assert_eq!(diagnostics().released_hook_events, 0);
+        assert_eq!(orphaned.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn panicking_orphan_handler_is_caught() {
+        let _lock = DIAGNOSTICS_LOCK.lock().unwrap();
+        let event_hook = fake_handle(0x2804);
+
+        reset_diagnostics();
+
+        set_orphan_handler(|_, _, _, _, _, _, _| panic!("orphan handler panic"));
+
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+        simulate_event(&event_hook, NamedEvent::ObjectHide);
+
+        clear_orphan_handler();
+
+        assert_eq!(diagnostics().unknown_hook_events, 2);
+    }
+
+    #[test]
+    fn released_hook_is_orphaned() {
+        let _lock = DIAGNOSTICS_LOCK.lock().unwrap();
+        let event_hook = fake_handle(0x2802);
+        let delivered = Arc::new(AtomicU64::new(0));
+
+        reset_diagnostics();
+
+        let event_data = counting_event_data(&delivered);
+        INSTALLED_HOOKS.insert(event_hook.clone(), &event_data);
+        drop(event_data);
+
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+
+        INSTALLED_HOOKS.remove(&event_hook);
+
+        assert_eq!(diagnostics().unknown_hook_events, 0);
+        assert_eq!(diagnostics().released_hook_events, 1);
+        assert_eq!(delivered.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn dispatch_races_install_and_uninstall() {
+        const DISPATCHERS: u64 = 4;
+        const EVENTS_PER_DISPATCHER: u64 = 1000;
+
+        let _lock = DIAGNOSTICS_LOCK.lock().unwrap();
+        let event_hook = fake_handle(0x2803);
+        let delivered = Arc::new(AtomicU64::new(0));
+        let event_data = counting_event_data(&delivered);
+
+        reset_diagnostics();
+
+        thread::scope(|scope| {
+            let churn = scope.spawn(|| {
+                for _ in 0..EVENTS_PER_DISPATCHER {
+                    INSTALLED_HOOKS.insert(event_hook.clone(), &event_data);
+                    INSTALLED_HOOKS.remove(&event_hook);
+                }
+            });
+
+            for _ in 0..DISPATCHERS {
+                scope.spawn(|| {
+                    for _ in 0..EVENTS_PER_DISPATCHER {
+                        simulate_event(&event_hook, NamedEvent::ObjectShow);
+                    }
+                });
+            }
+
+            churn.join().unwrap();
+        });
+
+        let diagnostics = diagnostics();
+
+        assert_eq!(diagnostics.released_hook_events, 0);
+        assert_eq!(
+            delivered.load(Ordering::SeqCst) + diagnostics.unknown_hook_events,
+            DISPATCHERS * EVENTS_PER_DISPATCHER
+        );
+    }
+
+    #[test]
+    fn dispatch_records_stats() {
+        let event_hook = fake_handle(0x3001);
+        let hook = SimulatedHook::register(
+            event_hook.clone(),
+            Box::new(|ev, _, _, _, _, _| {
+                if ev == Event::Named(NamedEvent::ObjectHide) {
+                    panic!("handler failure");
+                }
+            }),
+            Some(vec![
+                Event::Named(NamedEvent::ObjectShow),
+                Event::Named(NamedEvent::ObjectHide),
+            ]),
+        );
+
+        for event in [
+            NamedEvent::ObjectShow,
+            NamedEvent::ObjectShow,
+            NamedEvent::ObjectHide,
+            NamedEvent::ObjectNameChange,
+        ] {
+            simulate_event(&event_hook, event);
+        }
+
+        let stats = hook.stats();
+        let show = &stats.events[&Event::Named(NamedEvent::ObjectShow)];
+        let hide = &stats.events[&Event::Named(NamedEvent::ObjectHide)];
+        let name_change = &stats.events[&Event::Named(NamedEvent::ObjectNameChange)];
+
+        assert_eq!((show.received, show.filtered, show.delivered), (2, 0, 2));
+        assert_eq!((hide.delivered, hide.handler_panics), (1, 1));
+        assert_eq!((name_change.received, name_change.filtered), (1, 1));
+        assert_eq!(stats.total().latency.count(), 3);
+        assert!(stats.last_event().is_some());
+    }
+
+    #[test]
+    fn dispatch_records_receipt() {
+        let event_hook = fake_handle(0x3002);
+        let records = Arc::new(Mutex::new(Vec::new()));
+
+        let captured_records = records.clone();
+        let _hook = SimulatedHook::register(
+            event_hook.clone(),
+            Box::new(
+                move |ev, hwnd, id_object, id_child, id_event_thread, event_time| {
+                    captured_records.lock().unwrap().push(EventRecord::new(
+                        ev,
+                        hwnd,
                         id_object,
                         id_child,
                         id_event_thread,
                         event_time,
-                    );
+                    ));
+                },
+            ),
+            None,
+        );
+
+        let before = Instant::now();
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+
+        simulate_event(&event_hook, NamedEvent::ObjectHide);
+
+        let records = records.lock().unwrap();
+        assert!(records[0]
+            .received
+            .is_some_and(|received| received >= before));
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| record.sequence)
+                .collect::<Vec<_>>(),
+            [Some(0), Some(1)]
+        );
+        assert_eq!(receipt(), None);
+    }
+
+    #[test]
+    fn handoff_delivers_on_worker_and_counts_drops() {
+        let event_hook = fake_handle(0x3003);
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Mutex::new(release_rx);
+
+        let hook = SimulatedHook::register_with_handoff(
+            event_hook.clone(),
+            Box::new(move |_, _, _, _, _, _| {
+                let receipt = receipt().unwrap();
+                started_tx
+                    .send((thread::current().id(), receipt.sequence))
+                    .unwrap();
+                release_rx.lock().unwrap().recv().unwrap();
+            }),
+            None,
+            Some(Handoff {
+                capacity: 1,
+                overflow: Overflow::DropNewest,
+            }),
+            None,
+        )
+        .unwrap();
+
+        // the worker holds the first event in the handler, so the queue fills after one more
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+        let (worker, sequence) = started_rx.recv().unwrap();
+        assert_ne!(worker, thread::current().id());
+        assert_eq!(sequence, 0);
+
+        simulate_event(&event_hook, NamedEvent::ObjectShow);
+        simulate_event(&event_hook, NamedEvent::ObjectHide);
+
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        assert_eq!(started_rx.recv().unwrap().1, 1);
+
+        let stats = hook.stats();
+        let hide = &stats.events[&Event::Named(NamedEvent::ObjectHide)];
+        assert_eq!((hide.received, hide.dropped, hide.delivered), (1, 1, 0));
+
+        // uninstalling drains the queue, so nothing is delivered afterwards
+        drop(hook);
+        assert!(started_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn sharding_orders_by_window_and_delivers_in_parallel() {
+        const EVENTS: u64 = 20;
+
+        let event_hook = fake_handle(0x3004);
+        let shard = |hwnd| {
+            let queued = Queued {
+                event: Event::Named(NamedEvent::ObjectShow),
+                hwnd: WindowHandle::from_raw(hwnd),
+                id_object: 0,
+                id_child: 0,
+                id_event_thread: 0,
+                event_time: 0,
+                receipt: Receipt {
+                    received: Instant::now(),
+                    sequence: 0,
+                },
+            };
+
+            ShardKey::Window.shard(&queued, 2)
+        };
+        let slow = 0x10;
+        let fast = (0x11..).find(|hwnd| shard(*hwnd) != shard(slow)).unwrap();
+
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let (fast_tx, fast_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Mutex::new(release_rx);
+
+        let captured_delivered = delivered.clone();
+        let hook = SimulatedHook::register_with_handoff(
+            event_hook.clone(),
+            Box::new(move |_, hwnd, _, _, _, _| {
+                let sequence = receipt().unwrap().sequence;
+
+                match hwnd.to_raw() == slow {
+                    true if sequence == 0 => release_rx.lock().unwrap().recv().unwrap(),
+                    true => {}
+                    false => fast_tx.send(sequence).unwrap(),
                 }
-            }
-            // if we have no event filter always call the handler
-            None => {
-                event_handler(
-                    event,
-                    hwnd.into(),
-                    id_object,
-                    id_child,
-                    id_event_thread,
-                    event_time,
-                );
-            }
+
+                captured_delivered
+                    .lock()
+                    .unwrap()
+                    .push((hwnd.to_raw(), sequence));
+            }),
+            None,
+            None,
+            Some(Sharding {
+                workers: 2,
+                key: ShardKey::Window,
+            }),
+        )
+        .unwrap();
+
+        for sequence in 0..EVENTS {
+            let hwnd = match sequence % 2 {
+                0 => slow,
+                _ => fast,
+            };
+
+            dispatch(
+                event_hook.clone(),
+                NamedEvent::ObjectShow.into(),
+                WindowHandle::from_raw(hwnd),
+                0,
+                0,
+                0,
+                0,
+                Instant::now(),
+            );
+        }
+
+        // the fast window is delivered while the slow window's worker is held up
+        let fast_sequences = (0..EVENTS / 2)
+            .map(|_| fast_rx.recv().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(fast_sequences, (1..EVENTS).step_by(2).collect::<Vec<_>>());
+
+        // uninstalling drains the slow window's queue, in order
+        release_tx.send(()).unwrap();
+        drop(hook);
+
+        let delivered = delivered.lock().unwrap();
+        let slow_sequences = delivered
+            .iter()
+            .filter(|(hwnd, _)| *hwnd == slow)
+            .map(|(_, sequence)| *sequence)
+            .collect::<Vec<_>>();
+        assert_eq!(slow_sequences, (0..EVENTS).step_by(2).collect::<Vec<_>>());
+        assert_eq!(delivered.len() as u64, EVENTS);
+    }
+
+    #[test]
+    fn sharded_sequences_have_no_gaps() {
+        const EVENTS: u64 = 1000;
+
+        let event_hook = fake_handle(0x3005);
+        let sequences = Arc::new(Mutex::new(Vec::new()));
+
+        let captured_sequences = sequences.clone();
+        let hook = SimulatedHook::register_with_handoff(
+            event_hook.clone(),
+            Box::new(move |_, _, _, _, _, _| {
+                let sequence = receipt().unwrap().sequence;
+                captured_sequences.lock().unwrap().push(sequence);
+            }),
+            None,
+            None,
+            Some(Sharding {
+                workers: 4,
+                key: ShardKey::Window,
+            }),
+        )
+        .unwrap();
+
+        for hwnd in 0..EVENTS as usize {
+            dispatch(
+                event_hook.clone(),
+                NamedEvent::ObjectShow.into(),
+                WindowHandle::from_raw(hwnd % 16),
+                0,
+                0,
+                0,
+                0,
+                Instant::now(),
+            );
+        }
+
+        drop(hook);
+
+        let mut detector = GapDetector::starting_at(0).with_reorder_window(EVENTS);
+        for sequence in sequences.lock().unwrap().iter() {
+            assert_eq!(detector.observe(*sequence), vec![]);
         }
-    } else {
-        // it's theoretically possible for this to occur for os buffered events after we've uninstalled.
-        // As a result, this is implemented as a warning rather than panic.
-        warn!("Unable to find event handler with id: '{:?}'", event_hook.0);
+
+        assert_eq!(detector.finish(), vec![]);
+        assert_eq!(detector.missing(), 0);
+        assert_eq!(detector.next(), Some(EVENTS));
     }
 }
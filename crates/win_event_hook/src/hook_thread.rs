@@ -0,0 +1,323 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+};
+
+use tracing::trace;
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::WindowsAndMessaging::PostThreadMessageW,
+};
+
+use crate::{
+    config::Config,
+    errors::{Error, Result},
+    event_loop::{ensure_message_queue, run_event_loop, WM_HOOK_THREAD_COMMAND},
+    handler::EventHandler,
+    handles::OsHandle,
+    hook::{uninstall_handle, UnthreadedInner},
+};
+
+/// Commands that are processed, in order, by the event loop of a running [`HookThread`].
+enum Command {
+    /// Installs a hook on the thread, replying with the result.
+    Install(
+        Box<Config>,
+        Box<dyn EventHandler>,
+        Sender<Result<UnthreadedInner>>,
+    ),
+    /// Uninstalls a hook from the thread, replying with the result.
+    Uninstall(OsHandle, Sender<Result<()>>),
+    /// Runs a closure on the thread.
+    Run(Box<dyn FnOnce() + Send>),
+    /// Stops the event loop, ending the thread.
+    Shutdown,
+}
+
+/// A thread, managed by this library, that installs hooks and pumps their events.
+///
+/// A single [`HookThread`] can be shared by many hooks, by passing it to
+/// [`ConfigBuilder::with_hook_thread`](crate::config::ConfigBuilder::with_hook_thread) for each of them.
+/// Clones refer to the same underlying thread.
+///
+/// Note: The thread is started when the first hook using it is installed, and torn down when the last
+/// hook using it is uninstalled. Installing another hook afterwards starts a new thread.
+#[derive(Clone)]
+pub struct HookThread {
+    inner: Arc<HookThreadInner>,
+}
+
+struct HookThreadInner {
+    name: String,
+    state: Mutex<HookThreadState>,
+}
+
+#[derive(Default)]
+struct HookThreadState {
+    running: Option<RunningThread>,
+    hooks: usize,
+}
+
+/// The underlying thread of a [`HookThread`], while it is running.
+struct RunningThread {
+    tid: u32,
+    commands: Sender<Command>,
+    join_handle: JoinHandle<()>,
+}
+
+impl RunningThread {
+    /// Starts a new thread, returning once its event loop is able to receive commands.
+    fn start(name: &str) -> Result<Self> {
+        let (commands, commands_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        let join_handle = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || unsafe {
+                // ensure the thread can receive thread messages before reporting it is ready
+                ensure_message_queue();
+
+                let _ = ready_tx.send(GetCurrentThreadId());
+
+                run_event_loop(|| run_commands(&commands_rx));
+            })
+            .map_err(Error::Thread)?;
+
+        let tid = ready_rx.recv().map_err(|_| Error::HookThreadUnavailable)?;
+
+        trace!(?tid, "started hook thread");
+
+        Ok(Self {
+            tid,
+            commands,
+            join_handle,
+        })
+    }
+
+    /// Sends a [`Command`] to the thread, waking its event loop to process it.
+    fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| Error::HookThreadUnavailable)?;
+
+        unsafe { PostThreadMessageW(self.tid, WM_HOOK_THREAD_COMMAND, WPARAM(0), LPARAM(0)) }?;
+
+        Ok(())
+    }
+
+    /// Stops the thread, waiting for it to exit.
+    ///
+    /// Note: When called from the thread itself, this does not wait.
+    fn shutdown(self) -> Result<()> {
+        self.send(Command::Shutdown)?;
+
+        if unsafe { GetCurrentThreadId() } != self.tid {
+            self.join_handle
+                .join()
+                .map_err(|_| Error::HookThreadUnavailable)?;
+        }
+
+        trace!(tid = ?self.tid, "stopped hook thread");
+
+        Ok(())
+    }
+}
+
+impl HookThread {
+    /// Returns a new [`HookThread`], using the default thread name.
+    pub fn new() -> Self {
+        Self::with_name("WinEventHookThread")
+    }
+
+    /// Returns a new [`HookThread`], with a given name.
+    pub fn with_name(name: &str) -> Self {
+        Self {
+            inner: Arc::new(HookThreadInner {
+                name: name.to_string(),
+                state: Mutex::new(HookThreadState::default()),
+            }),
+        }
+    }
+
+    /// The name of the underlying thread.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The number of hooks currently installed on this thread.
+    pub fn hook_count(&self) -> usize {
+        self.state().hooks
+    }
+
+    /// Determines if the underlying thread is currently running.
+    pub fn is_running(&self) -> bool {
+        self.state().running.is_some()
+    }
+
+    /// Runs a given function on the underlying thread, blocking until it completes.
+    ///
+    /// Note: This fails with [`Error::HookThreadUnavailable`] if no hooks are installed on this thread.
+    /// When called from the underlying thread, the function is run immediately.
+    pub fn run<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let state = self.state();
+            let running = state.running.as_ref().ok_or(Error::HookThreadUnavailable)?;
+
+            if unsafe { GetCurrentThreadId() } == running.tid {
+                return Ok(f());
+            }
+
+            running.send(Command::Run(Box::new(move || {
+                let _ = tx.send(f());
+            })))?;
+        }
+
+        rx.recv().map_err(|_| Error::HookThreadUnavailable)
+    }
+
+    /// Installs a hook on this thread, starting the thread if needed.
+    pub(crate) fn install(
+        &self,
+        config: Config,
+        handler: Box<dyn EventHandler>,
+    ) -> Result<UnthreadedInner> {
+        let (tx, rx) = mpsc::channel();
+
+        let sent = {
+            let mut state = self.state();
+
+            if state.running.is_none() {
+                state.running = Some(RunningThread::start(&self.inner.name)?);
+            }
+
+            state.hooks += 1;
+
+            // A failure here indicates a library issue. Please open an issue on GitHub!
+            let running = state.running.as_ref().expect("Expected a running thread");
+
+            if unsafe { GetCurrentThreadId() } == running.tid {
+                tx.send(UnthreadedInner::new(config, handler))
+                    .map_err(|_| Error::HookThreadUnavailable)
+            } else {
+                running.send(Command::Install(Box::new(config), handler, tx))
+            }
+        };
+
+        let result = sent
+            .and_then(|_| rx.recv().map_err(|_| Error::HookThreadUnavailable))
+            .and_then(|result| result);
+
+        if result.is_err() {
+            self.release()?;
+        }
+
+        result
+    }
+
+    /// Uninstalls a hook from this thread, stopping the thread if it was the last one.
+    pub(crate) fn uninstall(&self, handle: OsHandle) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+
+        let sent = {
+            let state = self.state();
+            let running = state.running.as_ref().ok_or(Error::HookThreadUnavailable)?;
+
+            if unsafe { GetCurrentThreadId() } == running.tid {
+                tx.send(uninstall_handle(handle))
+                    .map_err(|_| Error::HookThreadUnavailable)
+            } else {
+                running.send(Command::Uninstall(handle, tx))
+            }
+        };
+
+        let result = sent
+            .and_then(|_| rx.recv().map_err(|_| Error::HookThreadUnavailable))
+            .and_then(|result| result);
+
+        self.release()?;
+
+        result
+    }
+
+    /// Unregisters a hook from this thread, stopping the thread if it was the last one.
+    fn release(&self) -> Result<()> {
+        let running = {
+            let mut state = self.state();
+
+            state.hooks = state.hooks.saturating_sub(1);
+
+            match state.hooks {
+                0 => state.running.take(),
+                _ => None,
+            }
+        };
+
+        // shutdown happens outside of the state lock, as the thread may still need it to finish up
+        match running {
+            Some(running) => running.shutdown(),
+            None => Ok(()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, HookThreadState> {
+        // A failure here indicates a library issue. Please open an issue on GitHub!
+        self.inner
+            .state
+            .lock()
+            .expect("Unable to obtain hook thread lock")
+    }
+}
+
+impl Default for HookThread {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for HookThread {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HookThread")
+            .field("name", &self.inner.name)
+            .field("hooks", &self.hook_count())
+            .finish()
+    }
+}
+
+impl PartialEq for HookThread {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for HookThread {}
+
+/// Processes all pending [`Command`]s, in order.
+///
+/// Returns `false` once a [`Command::Shutdown`] is processed, indicating the event loop should stop.
+fn run_commands(commands: &Receiver<Command>) -> bool {
+    while let Ok(command) = commands.try_recv() {
+        match command {
+            Command::Install(config, handler, reply) => {
+                let _ = reply.send(UnthreadedInner::new(*config, handler));
+            }
+            Command::Uninstall(handle, reply) => {
+                let _ = reply.send(uninstall_handle(handle));
+            }
+            Command::Run(f) => f(),
+            Command::Shutdown => return false,
+        }
+    }
+
+    true
+}
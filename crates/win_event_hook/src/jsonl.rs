@@ -0,0 +1,343 @@
+//! Import and export of [`EventRecord`]s as [JSON Lines](https://jsonlines.org/), for ad-hoc analysis.
+//!
+//! Each record is written as a single JSON object, on its own line. Version 1 of the schema contains:
+//!
+//! | Field         | Type             | Description                                                            |
+//! |---------------|------------------|------------------------------------------------------------------------|
+//! | `v`           | number           | The schema version, currently `1`.                                     |
+//! | `event`       | string           | The event name, for instance `ObjectShow`; see [`Event`]'s `Display`.  |
+//! | `id`          | number           | The raw event id.                                                      |
+//! | `category`    | string           | The event category, for instance `named`; see [`EventCategory::name`]. |
+//! | `hwnd`        | string           | The window handle, as hex, for instance `0x000A01B2`.                  |
+//! | `object`      | number           | The raw object id.                                                     |
+//! | `object_name` | string or `null` | The standard object id name, for instance `OBJID_CLIENT`.              |
+//! | `child`       | number           | The raw child id.                                                      |
+//! | `child_name`  | string or `null` | `CHILDID_SELF` if the event was triggered by the object itself.        |
+//! | `thread`      | number           | The id of the thread that generated the event.                         |
+//! | `time`        | number           | The time, in milliseconds since the system started, of the event.      |
+//! | `seq`         | number, optional | The sequence number of the event within its hook, if it's known.       |
+//!
+//! When parsing, `id`, `hwnd`, `object`, `child`, `thread` and `time` are required, and the
+//! remaining fields are informational. Unknown fields are ignored, so that fields can be added
+//! without a new schema version. A missing `v` is treated as version `1`.
+//!
+//! [`EventCategory::name`]: crate::events::EventCategory::name
+
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    errors::{Error, Result},
+    events::Event,
+    handler::EventHandler,
+    handles::WindowHandle,
+    objects::{ObjectId, CHILD_ID_SELF},
+    record::EventRecord,
+};
+
+/// The version of the schema written by this module.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// An [`EventRecord`], as it is represented in JSON.
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    #[serde(default = "default_version")]
+    v: u32,
+    #[serde(default)]
+    event: Option<String>,
+    id: u32,
+    #[serde(default)]
+    category: Option<String>,
+    hwnd: String,
+    object: i32,
+    #[serde(default)]
+    object_name: Option<String>,
+    child: i32,
+    #[serde(default)]
+    child_name: Option<String>,
+    thread: u32,
+    time: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seq: Option<u64>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+impl From<&EventRecord> for JsonRecord {
+    fn from(record: &EventRecord) -> Self {
+        Self {
+            v: SCHEMA_VERSION,
+            event: Some(record.event.to_string()),
+            id: record.event.into(),
+            category: Some(record.event.category().name().to_string()),
+            hwnd: format!("0x{:08X}", record.hwnd.to_raw()),
+            object: record.id_object,
+            object_name: ObjectId::from(record.id_object).name().map(str::to_string),
+            child: record.id_child,
+            child_name: (record.id_child == CHILD_ID_SELF).then(|| "CHILDID_SELF".to_string()),
+            thread: record.id_event_thread,
+            time: record.event_time,
+            seq: record.sequence,
+        }
+    }
+}
+
+/// Formats a given [`EventRecord`] as a single line of JSON, without a trailing newline.
+pub fn to_line(record: &EventRecord) -> String {
+    // A failure here indicates a library issue. Please open an issue on GitHub!
+    serde_json::to_string(&JsonRecord::from(record)).expect("Unable to serialize event record")
+}
+
+/// Parses an [`EventRecord`] from a single line of JSON.
+pub fn from_line(line: &str) -> Result<EventRecord> {
+    parse_line(line, 1)
+}
+
+fn parse_line(line: &str, line_number: usize) -> Result<EventRecord> {
+    let invalid = |reason: String| Error::InvalidJsonRecord {
+        line: line_number,
+        reason,
+    };
+
+    let json: JsonRecord = serde_json::from_str(line).map_err(|err| invalid(err.to_string()))?;
+
+    if json.v == 0 || json.v > SCHEMA_VERSION {
+        return Err(invalid(format!("unsupported schema version '{}'", json.v)));
+    }
+
+    let hwnd = json.hwnd.trim();
+    let hwnd = hwnd
+        .strip_prefix("0x")
+        .or_else(|| hwnd.strip_prefix("0X"))
+        .ok_or_else(|| invalid(format!("hwnd '{hwnd}' is not hex")))?;
+    let hwnd = usize::from_str_radix(hwnd, 16)
+        .map_err(|err| invalid(format!("hwnd '{hwnd}' is not hex: {err}")))?;
+
+    // built directly, as `EventRecord::new` would take the receipt of a handler that's parsing lines
+    Ok(EventRecord {
+        event: Event::from(json.id),
+        hwnd: WindowHandle::from_raw(hwnd),
+        id_object: json.object,
+        id_child: json.child,
+        id_event_thread: json.thread,
+        event_time: json.time,
+        received: None,
+        sequence: json.seq,
+    })
+}
+
+/// Writes [`EventRecord`]s as JSON Lines, as they occur.
+pub struct JsonlWriter<W: Write> {
+    writer: W,
+    records_written: u64,
+    failed_writes: u64,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    /// Returns a new [`JsonlWriter`].
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            records_written: 0,
+            failed_writes: 0,
+        }
+    }
+
+    /// Writes a given [`EventRecord`], as a single line.
+    pub fn write(&mut self, record: &EventRecord) -> Result<()> {
+        let mut line = to_line(record);
+        line.push('\n');
+
+        match self.writer.write_all(line.as_bytes()) {
+            Ok(()) => {
+                self.records_written += 1;
+
+                Ok(())
+            }
+            Err(err) => {
+                self.failed_writes += 1;
+
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// The number of records successfully written.
+    pub fn records_written(&self) -> u64 {
+        self.records_written
+    }
+
+    /// The number of records that could not be written.
+    pub fn failed_writes(&self) -> u64 {
+        self.failed_writes
+    }
+
+    /// Flushes, and returns, the underlying writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Send + 'static> JsonlWriter<W> {
+    /// Returns an [`EventHandler`] that writes each event it receives to a given [`JsonlWriter`].
+    ///
+    /// Note: Failures are logged and counted (see [`Self::failed_writes`]), as they cannot be returned to the hook.
+    pub fn handler(writer: &Arc<Mutex<Self>>) -> impl EventHandler {
+        let writer = writer.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            let mut writer = writer.lock().expect("Unable to obtain jsonl writer lock");
+
+            if let Err(err) = writer.write(&record) {
+                warn!(?err, ?event, "failed to write event as jsonl");
+            }
+        }
+    }
+}
+
+/// Reads [`EventRecord`]s from JSON Lines.
+///
+/// Records are obtained by iterating over the reader. Blank lines are skipped, and lines that
+/// cannot be parsed are returned as errors, without ending iteration.
+pub struct JsonlReader<R: BufRead> {
+    reader: R,
+    line_number: usize,
+}
+
+impl<R: BufRead> JsonlReader<R> {
+    /// Returns a new [`JsonlReader`].
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlReader<R> {
+    type Item = Result<EventRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            self.line_number += 1;
+
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => return Some(parse_line(&line, self.line_number)),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{from_line, to_line, JsonlReader, JsonlWriter};
+    use crate::{
+        errors::Error,
+        events::{Event, NamedEvent, UiaEvent},
+        handler::{with_receipt, Receipt},
+        handles::WindowHandle,
+        record::EventRecord,
+    };
+
+    #[test]
+    fn jsonl_round_trips() {
+        let records = vec![
+            EventRecord::new(
+                Event::Named(NamedEvent::ObjectShow),
+                WindowHandle::from_raw(0xA01B2),
+                -4,
+                0,
+                1234,
+                5678,
+            ),
+            EventRecord {
+                sequence: Some(7),
+                ..EventRecord::new(
+                    Event::Uia(UiaEvent::try_from(0x4E00).unwrap()),
+                    WindowHandle::from_raw(0x1_0000_0000),
+                    7,
+                    -3,
+                    1,
+                    u32::MAX,
+                )
+            },
+        ];
+
+        let mut writer = JsonlWriter::new(Vec::new());
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        let bytes = writer.into_inner().unwrap();
+
+        let parsed = JsonlReader::new(bytes.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(parsed, records);
+
+        // lines parsed within a handler don't take on its receipt
+        let receipt = Receipt {
+            received: Instant::now(),
+            sequence: 3,
+        };
+        let parsed = with_receipt(receipt, || from_line(&to_line(&records[0]))).unwrap();
+        assert_eq!((parsed.received, parsed.sequence), (None, None));
+
+        assert_eq!(
+            to_line(&records[0]),
+            r#"{"v":1,"event":"ObjectShow","id":32770,"category":"named","hwnd":"0x000A01B2","object":-4,"object_name":"OBJID_CLIENT","child":0,"child_name":"CHILDID_SELF","thread":1234,"time":5678}"#
+        );
+    }
+
+    #[test]
+    fn jsonl_reports_invalid_lines() {
+        let input = "\n{\"id\":32770,\"hwnd\":\"0x1\",\"object\":0,\"child\":0,\"thread\":1,\"time\":2}\n{\"v\":2}\n{\"id\":1,\"hwnd\":\"12\",\"object\":0,\"child\":0,\"thread\":1,\"time\":2}\n";
+        let results = JsonlReader::new(input.as_bytes()).collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap().event,
+            Event::Named(NamedEvent::ObjectShow)
+        );
+        assert!(matches!(
+            results[1],
+            Err(Error::InvalidJsonRecord { line: 3, .. })
+        ));
+        assert!(matches!(
+            results[2],
+            Err(Error::InvalidJsonRecord { line: 4, .. })
+        ));
+
+        assert!(from_line("not json").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Measurement of delivery lag; the time between the os raising an event and the hook receiving it.
+//!
+//! Out-of-context hooks receive events through the message queue of the installing thread, so events
+//! may be delivered long after they were raised when that thread, or the system, is busy.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::{debug, info, warn};
+
+use crate::{
+    events::Event,
+    handler::{EventHandler, LagAlertHandler},
+    record::EventRecord,
+    stats::LatencyHistogram,
+    time::EventClock,
+};
+
+/// An event delivered with more lag than a [`LatencyTracker`] threshold allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LagAlert {
+    /// The late event.
+    pub record: EventRecord,
+    /// The lag of the event.
+    pub lag: Duration,
+    /// The threshold the lag exceeded.
+    pub threshold: Duration,
+}
+
+/// Tracks the delivery lag of events, using the time each [`EventRecord`] was received.
+///
+/// Note: Event times have a resolution of ~10-16 milliseconds, so lag is only accurate to within that.
+pub struct LatencyTracker {
+    clock: EventClock,
+    threshold: Option<Duration>,
+    on_alert: Option<Box<dyn LagAlertHandler>>,
+    lag: LatencyHistogram,
+    lag_by_event: HashMap<Event, LatencyHistogram>,
+    max: Option<Duration>,
+    exceeded: u64,
+    /// The number of consecutive events recorded with more lag than the threshold.
+    late: u64,
+}
+
+impl LatencyTracker {
+    /// Returns a new [`LatencyTracker`], resolving event times with a given [`EventClock`].
+    ///
+    /// Note: This should be the clock of the hook being tracked, see [`WinEventHook::clock`](crate::WinEventHook::clock).
+    pub fn new(clock: EventClock) -> Self {
+        Self {
+            clock,
+            threshold: None,
+            on_alert: None,
+            lag: LatencyHistogram::default(),
+            lag_by_event: HashMap::new(),
+            max: None,
+            exceeded: 0,
+            late: 0,
+        }
+    }
+
+    /// Sets a threshold, above which lag is counted (see [`Self::exceeded`]).
+    ///
+    /// Note: A warning is logged when lag first exceeds the threshold, rather than for every late event, so
+    /// that a backlog doesn't flood the log. Use [`Self::with_alert`] to act on each late event.
+    pub fn with_threshold(self, threshold: Duration) -> Self {
+        Self {
+            threshold: Some(threshold),
+            ..self
+        }
+    }
+
+    /// Sets a [`LagAlertHandler`] function, called with a [`LagAlert`] when lag exceeds the threshold.
+    ///
+    /// Note: This is called while recording, so should return quickly.
+    pub fn with_alert<F: LagAlertHandler + 'static>(self, on_alert: F) -> Self {
+        Self {
+            on_alert: Some(Box::new(on_alert)),
+            ..self
+        }
+    }
+
+    /// Records the lag of a given [`EventRecord`], returning it.
+    ///
+    /// Note: Records without a receipt time (for instance, those read from a capture) are ignored.
+    pub fn record(&mut self, record: &EventRecord) -> Option<Duration> {
+        let received = record.received?;
+        let raised = self
+            .clock
+            .resolve_at(record.event_time, received)
+            .to_instant();
+        let lag = received.saturating_duration_since(raised);
+
+        self.lag.record(lag);
+        self.lag_by_event
+            .entry(record.event)
+            .or_default()
+            .record(lag);
+        self.max = self.max.max(Some(lag));
+
+        let Some(threshold) = self.threshold else {
+            return Some(lag);
+        };
+
+        if lag <= threshold {
+            if self.late > 0 {
+                info!(
+                    late = self.late,
+                    ?threshold,
+                    "events no longer delivered late"
+                );
+
+                self.late = 0;
+            }
+
+            return Some(lag);
+        }
+
+        self.exceeded += 1;
+        self.late += 1;
+
+        match self.late {
+            1 => warn!(event = ?record.event, ?lag, ?threshold, "events delivered late"),
+            _ => debug!(event = ?record.event, ?lag, ?threshold, "event delivered late"),
+        }
+
+        if let Some(on_alert) = &self.on_alert {
+            on_alert(&LagAlert {
+                record: record.clone(),
+                lag,
+                threshold,
+            });
+        }
+
+        Some(lag)
+    }
+
+    /// The distribution of lag, for all events recorded.
+    pub fn lag(&self) -> &LatencyHistogram {
+        &self.lag
+    }
+
+    /// The distribution of lag, for each [`Event`] recorded.
+    pub fn lag_by_event(&self) -> &HashMap<Event, LatencyHistogram> {
+        &self.lag_by_event
+    }
+
+    /// The largest lag recorded, if any.
+    pub fn max(&self) -> Option<Duration> {
+        self.max
+    }
+
+    /// The number of events recorded with more lag than the threshold.
+    pub fn exceeded(&self) -> u64 {
+        self.exceeded
+    }
+
+    /// Clears the lag recorded so far.
+    ///
+    /// Note: This is useful for reporting lag over an interval.
+    pub fn reset(&mut self) {
+        self.lag = LatencyHistogram::default();
+        self.lag_by_event.clear();
+        self.max = None;
+        self.exceeded = 0;
+        self.late = 0;
+    }
+
+    /// Returns an [`EventHandler`] that records the lag of each event it receives with a given [`LatencyTracker`].
+    pub fn handler(tracker: &Arc<Mutex<Self>>) -> impl EventHandler {
+        let tracker = tracker.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            tracker
+                .lock()
+                .expect("Unable to obtain latency tracker lock")
+                .record(&record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant, SystemTime},
+    };
+
+    use tracing_test::traced_test;
+
+    use super::LatencyTracker;
+    use crate::{
+        events::{Event, NamedEvent},
+        handles::WindowHandle,
+        record::EventRecord,
+        time::EventClock,
+    };
+
+    #[test]
+    fn records_lag_and_alerts() {
+        let installed = Instant::now();
+        let alerts = Arc::new(AtomicU64::new(0));
+
+        let captured_alerts = alerts.clone();
+        let mut tracker = LatencyTracker::new(EventClock::new(1000, SystemTime::now(), installed))
+            .with_threshold(Duration::from_millis(200))
+            .with_alert(move |alert| {
+                assert_eq!(alert.lag, Duration::from_millis(250));
+                captured_alerts.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let record = |event: NamedEvent, event_time: u32, received: u64| EventRecord {
+            received: Some(installed + Duration::from_millis(received)),
+            ..EventRecord::new(
+                Event::Named(event),
+                WindowHandle::default(),
+                0,
+                0,
+                1,
+                event_time,
+            )
+        };
+
+        assert_eq!(
+            tracker.record(&record(NamedEvent::ObjectShow, 1100, 350)),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(
+            tracker.record(&record(NamedEvent::ObjectHide, 1200, 210)),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(
+            tracker.record(&EventRecord {
+                received: None,
+                ..record(NamedEvent::ObjectHide, 1200, 0)
+            }),
+            None
+        );
+
+        assert_eq!(tracker.lag().count(), 2);
+        assert_eq!(tracker.lag_by_event().len(), 2);
+        assert_eq!(tracker.max(), Some(Duration::from_millis(250)));
+        assert_eq!(tracker.exceeded(), 1);
+        assert_eq!(alerts.load(Ordering::SeqCst), 1);
+
+        tracker.reset();
+        assert_eq!((tracker.lag().count(), tracker.exceeded()), (0, 0));
+    }
+
+    #[traced_test]
+    #[test]
+    fn warns_once_per_late_streak() {
+        let installed = Instant::now();
+        let mut tracker = LatencyTracker::new(EventClock::new(1000, SystemTime::now(), installed))
+            .with_threshold(Duration::from_millis(200));
+
+        let record = |lag: u64| EventRecord {
+            received: Some(installed + Duration::from_millis(lag)),
+            ..EventRecord::new(
+                Event::Named(NamedEvent::ObjectShow),
+                WindowHandle::default(),
+                0,
+                0,
+                1,
+                1000,
+            )
+        };
+
+        for lag in [250, 300, 350, 10, 250] {
+            tracker.record(&record(lag));
+        }
+
+        assert_eq!(tracker.exceeded(), 4);
+        assert!(logs_contain("late=3"));
+        logs_assert(|lines| {
+            match lines
+                .iter()
+                .filter(|line| line.contains("WARN") && line.contains("events delivered late"))
+                .count()
+            {
+                2 => Ok(()),
+                count => Err(format!("expected 2 warnings, got {count}")),
+            }
+        });
+    }
+}
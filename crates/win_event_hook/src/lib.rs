@@ -4,17 +4,45 @@ pub use config::Config;
 use errors::{Error, Result};
 pub use handler::EventHandler;
 use handles::Handle;
-use hook::{ThreadedInner, UnthreadedInner, WinEventHookInner};
+use hook::WinEventHookInner;
+#[cfg(windows)]
+pub use hook_thread::HookThread;
+use stats::Stats;
+use time::EventClock;
 use tracing::trace;
 
+pub mod capture;
 pub mod config;
+pub mod diagnostics;
 pub mod errors;
+#[cfg(windows)]
 mod event_loop;
 pub mod events;
 pub mod flags;
 pub mod handler;
 pub mod handles;
+pub mod handoff;
 mod hook;
+#[cfg(windows)]
+mod hook_thread;
+#[cfg(feature = "jsonl")]
+pub mod jsonl;
+pub mod latency;
+pub mod merge;
+pub mod objects;
+pub mod record;
+mod registry;
+pub mod replay;
+pub mod sampling;
+pub mod sequence;
+pub mod stats;
+pub mod time;
+
+/// Library internals, exposed for benchmarks. Not part of the public API, and may change at any time.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::hook::{simulate_event, SimulatedHook};
+}
 
 /// A Windows Event Hook, managed using the
 /// [SetWinEventHook](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwineventhook)
@@ -23,6 +51,7 @@ mod hook;
 /// Windows API functions.
 pub struct WinEventHook {
     inner: Box<dyn WinEventHookInner>,
+    clock: EventClock,
 }
 
 impl WinEventHook {
@@ -36,9 +65,32 @@ impl WinEventHook {
         self.inner.installed()
     }
 
+    /// Obtains the runtime [`Stats`] of the hook, since it was installed or since they were last reset.
+    pub fn stats(&self) -> Stats {
+        self.inner.stats().snapshot()
+    }
+
+    /// Resets the runtime [`Stats`] of the hook, returning the values prior to the reset.
+    ///
+    /// Note: This is useful for reporting statistics over an interval. Events dispatched at the moment of the
+    /// reset may be counted in neither interval.
+    pub fn reset_stats(&self) -> Stats {
+        self.inner.stats().reset()
+    }
+
+    /// Obtains the [`EventClock`] anchored when the hook was installed, which converts the `event_time`
+    /// passed to the [`EventHandler`] to wall-clock time.
+    ///
+    /// Note: To convert times within the handler, create an [`EventClock`] before installing the hook.
+    pub fn clock(&self) -> EventClock {
+        self.clock
+    }
+
     /// Installs a hook, using a given [`Config`] and [`EventHandler`] function.
     ///
     /// Note: [`Config`] can be created using the builder pattern, with [`Config::builder`].
+    ///
+    /// Note: Hooks can only be installed on windows. Elsewhere, this fails with [`Error::Unsupported`].
     pub fn install<F: EventHandler + 'static>(config: Config, handler: F) -> Result<Self> {
         trace!(?config, "validating config");
 
@@ -48,11 +100,11 @@ impl WinEventHook {
 
         trace!("config valid, attempting to install hook");
 
+        let clock = EventClock::now();
+
         Ok(Self {
-            inner: match config.dedicated_thread_name.is_none() {
-                true => Box::new(UnthreadedInner::new(config, Box::new(handler))?),
-                false => Box::new(ThreadedInner::new(config, Box::new(handler))?),
-            },
+            inner: hook::install(config, Box::new(handler))?,
+            clock,
         })
     }
 
@@ -84,7 +136,7 @@ impl Hash for WinEventHook {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, windows))]
 mod tests {
 
     use tracing::info;
@@ -92,7 +144,7 @@ mod tests {
 
     use super::{
         events::{Event, NamedEvent},
-        Config, WinEventHook,
+        Config, HookThread, WinEventHook,
     };
 
     #[test]
@@ -115,4 +167,56 @@ mod tests {
 
         hook.uninstall().unwrap();
     }
+
+    #[test]
+    fn can_share_hook_thread() {
+        let hook_thread = HookThread::with_name("SharedHookThread");
+
+        let cfg = Config::builder()
+            .with_event(Event::Named(NamedEvent::ObjectShow))
+            .with_hook_thread(&hook_thread)
+            .finish();
+
+        let mut first = WinEventHook::install(cfg.clone(), |ev, _, _, _, _, _| {
+            info!(?ev, "first got event")
+        })
+        .unwrap();
+        let mut second =
+            WinEventHook::install(cfg, |ev, _, _, _, _, _| info!(?ev, "second got event")).unwrap();
+
+        assert!(hook_thread.is_running());
+        assert_eq!(hook_thread.hook_count(), 2);
+
+        first.uninstall().unwrap();
+
+        assert!(hook_thread.is_running());
+        assert_eq!(hook_thread.hook_count(), 1);
+
+        second.uninstall().unwrap();
+
+        assert!(!hook_thread.is_running());
+        assert_eq!(hook_thread.hook_count(), 0);
+    }
+
+    #[test]
+    fn can_run_on_hook_thread() {
+        let hook_thread = HookThread::with_name("RunHookThread");
+
+        assert!(hook_thread.run(|| ()).is_err());
+
+        let cfg = Config::builder()
+            .with_event(Event::Named(NamedEvent::ObjectShow))
+            .with_hook_thread(&hook_thread)
+            .finish();
+
+        let mut hook = WinEventHook::install(cfg, |_, _, _, _, _, _| {}).unwrap();
+
+        let thread_name = hook_thread
+            .run(|| std::thread::current().name().map(str::to_string))
+            .unwrap();
+
+        assert_eq!(thread_name.as_deref(), Some("RunHookThread"));
+
+        hook.uninstall().unwrap();
+    }
 }
@@ -0,0 +1,386 @@
+//! Merging of events from several hooks (or recordings) into a single stream, ordered by event time.
+//!
+//! Each hook delivers its own events in order, but hooks on different threads are delivered
+//! independently, so their streams interleave without a global order. A [`Merger`] holds records back
+//! for a bounded reorder window, releasing them in order of their `event_time` once no earlier record
+//! is expected. Records that arrive after the window has passed them are released as [`Merged::Late`].
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    iter::Peekable,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::debug;
+
+use crate::{handler::EventHandler, record::EventRecord};
+
+/// A record released by a [`Merger`], with the index of the source it was pushed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Merged {
+    /// The record is in order, relative to all other records released.
+    Ordered { source: usize, record: EventRecord },
+    /// The record arrived after later records were released, as it was later than the window allows.
+    Late { source: usize, record: EventRecord },
+}
+
+impl Merged {
+    /// The index of the source the record was pushed from.
+    pub fn source(&self) -> usize {
+        match self {
+            Merged::Ordered { source, .. } | Merged::Late { source, .. } => *source,
+        }
+    }
+
+    /// The record.
+    pub fn record(&self) -> &EventRecord {
+        match self {
+            Merged::Ordered { record, .. } | Merged::Late { record, .. } => record,
+        }
+    }
+}
+
+/// A record waiting to be released, ordered so that the earliest is at the top of a [`BinaryHeap`].
+#[derive(Debug)]
+struct Pending {
+    time: i64,
+    arrival: u64,
+    source: usize,
+    record: EventRecord,
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // records with the same time are released in the order they arrived
+        (other.time, other.arrival).cmp(&(self.time, self.arrival))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+/// Orders records from several sources by `event_time`, within a bounded reorder window.
+///
+/// Note: Event times wrap every ~49.7 days, so they're ordered relative to the latest time seen.
+#[derive(Debug)]
+pub struct Merger {
+    window: i64,
+    pending: BinaryHeap<Pending>,
+    late: VecDeque<Merged>,
+    /// The latest time pushed or advanced to, as milliseconds that don't wrap.
+    latest: Option<i64>,
+    /// The time of the latest record released in order.
+    released: Option<i64>,
+    arrivals: u64,
+    late_count: u64,
+}
+
+impl Merger {
+    /// Returns a new [`Merger`], holding records for up to a given reorder window.
+    ///
+    /// Note: Event times have a resolution of ~10-16 milliseconds, so small windows reorder little.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: window.as_millis().try_into().unwrap_or(i64::MAX),
+            pending: BinaryHeap::new(),
+            late: VecDeque::new(),
+            latest: None,
+            released: None,
+            arrivals: 0,
+            late_count: 0,
+        }
+    }
+
+    /// Adds a record from a given source.
+    pub fn push(&mut self, source: usize, record: EventRecord) {
+        let time = self.extend(record.event_time);
+        self.latest = self.latest.max(Some(time));
+
+        if self.released.is_some_and(|released| time < released) {
+            debug!(
+                ?source,
+                event_time = record.event_time,
+                "record arrived late"
+            );
+
+            self.late_count += 1;
+            self.late.push_back(Merged::Late { source, record });
+
+            return;
+        }
+
+        self.pending.push(Pending {
+            time,
+            arrival: self.arrivals,
+            source,
+            record,
+        });
+        self.arrivals += 1;
+    }
+
+    /// Advances the merge as though a record with a given `event_time` was pushed, releasing records
+    /// older than the window.
+    ///
+    /// Note: Live sources should call this periodically with the current tick count (see
+    /// [`EventClock::ticks_at`](crate::time::EventClock::ticks_at)), so records are released when events are quiet.
+    pub fn advance(&mut self, event_time: u32) {
+        let time = self.extend(event_time);
+        self.latest = self.latest.max(Some(time));
+    }
+
+    /// Releases the next record, if one is late, or older than the window.
+    pub fn pop(&mut self) -> Option<Merged> {
+        if let Some(late) = self.late.pop_front() {
+            return Some(late);
+        }
+
+        let latest = self.latest?;
+        if self.pending.peek()?.time > latest.saturating_sub(self.window) {
+            return None;
+        }
+
+        self.release()
+    }
+
+    /// Releases the next record, regardless of the window.
+    ///
+    /// Note: This is for use once every source has ended, to release the records still held.
+    pub fn flush(&mut self) -> Option<Merged> {
+        match self.late.pop_front() {
+            Some(late) => Some(late),
+            None => self.release(),
+        }
+    }
+
+    /// The number of records held, waiting to be released.
+    pub fn pending(&self) -> usize {
+        self.pending.len() + self.late.len()
+    }
+
+    /// The number of records that arrived later than the window allowed.
+    pub fn late(&self) -> u64 {
+        self.late_count
+    }
+
+    /// Returns an [`EventHandler`] that pushes each event it receives to a given [`Merger`], from a given source.
+    ///
+    /// Note: Records are released by calling [`Self::pop`], for instance from another thread.
+    pub fn handler(merger: &Arc<Mutex<Self>>, source: usize) -> impl EventHandler {
+        let merger = merger.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            merger
+                .lock()
+                .expect("Unable to obtain merger lock")
+                .push(source, record);
+        }
+    }
+
+    fn release(&mut self) -> Option<Merged> {
+        let Pending {
+            time,
+            source,
+            record,
+            ..
+        } = self.pending.pop()?;
+
+        self.released = self.released.max(Some(time));
+
+        Some(Merged::Ordered { source, record })
+    }
+
+    /// Converts an `event_time` to milliseconds that don't wrap, relative to the latest time seen.
+    fn extend(&self, event_time: u32) -> i64 {
+        match self.latest {
+            Some(latest) => latest + i64::from(event_time.wrapping_sub(latest as u32) as i32),
+            None => i64::from(event_time),
+        }
+    }
+}
+
+/// Merges recorded streams (for instance, captures), ordered by `event_time` within a given reorder window.
+///
+/// Note: Each source is read as its records are needed, so sources may be arbitrarily large.
+pub fn merge<I: Iterator<Item = EventRecord>>(
+    sources: impl IntoIterator<Item = I>,
+    window: Duration,
+) -> Merge<I> {
+    Merge {
+        sources: sources.into_iter().map(Iterator::peekable).collect(),
+        merger: Merger::new(window),
+    }
+}
+
+/// An iterator over merged records, see [`merge`].
+pub struct Merge<I: Iterator<Item = EventRecord>> {
+    sources: Vec<Peekable<I>>,
+    merger: Merger,
+}
+
+impl<I: Iterator<Item = EventRecord>> Merge<I> {
+    /// Obtains the [`Merger`], for instance to read the number of late records.
+    pub fn merger(&self) -> &Merger {
+        &self.merger
+    }
+}
+
+impl<I: Iterator<Item = EventRecord>> Iterator for Merge<I> {
+    type Item = Merged;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(merged) = self.merger.pop() {
+                return Some(merged);
+            }
+
+            // read from the source with the earliest next record, so the window only absorbs
+            // disorder within a source
+            let merger = &self.merger;
+            let earliest = self
+                .sources
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, source)| {
+                    Some((merger.extend(source.peek()?.event_time), index))
+                })
+                .min();
+
+            match earliest {
+                Some((_, index)) => {
+                    let record = self.sources[index].next()?;
+                    self.merger.push(index, record);
+                }
+                None => return self.merger.flush(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{merge, Merged, Merger};
+    use crate::{
+        events::{Event, NamedEvent},
+        handles::WindowHandle,
+        record::EventRecord,
+    };
+
+    fn record(event_time: u32) -> EventRecord {
+        EventRecord::new(
+            Event::Named(NamedEvent::ObjectShow),
+            WindowHandle::default(),
+            0,
+            0,
+            1,
+            event_time,
+        )
+    }
+
+    fn times(merged: impl IntoIterator<Item = Merged>) -> Vec<(usize, u32, bool)> {
+        merged
+            .into_iter()
+            .map(|merged| {
+                (
+                    merged.source(),
+                    merged.record().event_time,
+                    matches!(merged, Merged::Late { .. }),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_within_window_and_reports_late() {
+        let mut merger = Merger::new(Duration::from_millis(100));
+
+        merger.push(0, record(1000));
+        merger.push(1, record(1050));
+        merger.push(0, record(1020));
+        assert_eq!(merger.pop(), None);
+
+        // 1000 and 1020 are now older than the window
+        merger.push(1, record(1130));
+        let released = times(std::iter::from_fn(|| merger.pop()));
+        assert_eq!(released, vec![(0, 1000, false), (0, 1020, false)]);
+
+        // earlier than a released record
+        merger.push(0, record(1010));
+        merger.advance(1250);
+        let released = times(std::iter::from_fn(|| merger.pop()));
+        assert_eq!(
+            released,
+            vec![(0, 1010, true), (1, 1050, false), (1, 1130, false)]
+        );
+
+        assert_eq!(merger.late(), 1);
+        assert_eq!(merger.pending(), 0);
+    }
+
+    #[test]
+    fn merges_streams_across_wrap_boundary() {
+        let first = vec![record(u32::MAX - 20), record(5), record(30)];
+        let second = vec![record(u32::MAX - 10), record(u32::MAX), record(20)];
+
+        let merged = merge([first.into_iter(), second.into_iter()], Duration::ZERO);
+
+        assert_eq!(
+            times(merged),
+            vec![
+                (0, u32::MAX - 20, false),
+                (1, u32::MAX - 10, false),
+                (1, u32::MAX, false),
+                (0, 5, false),
+                (1, 20, false),
+                (0, 30, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_absorbs_disorder_within_source() {
+        let first = vec![record(100), record(80), record(300)];
+        let second = vec![record(90), record(200)];
+
+        let mut merged = merge(
+            [first.into_iter(), second.into_iter()],
+            Duration::from_millis(50),
+        );
+        let released = times(merged.by_ref());
+
+        assert_eq!(
+            released,
+            vec![
+                (0, 80, false),
+                (1, 90, false),
+                (0, 100, false),
+                (1, 200, false),
+                (0, 300, false),
+            ]
+        );
+        assert_eq!(merged.merger().late(), 0);
+    }
+}
@@ -0,0 +1,126 @@
+/// The `id_child` value indicating an event was triggered by the object itself, rather than a child element.
+pub const CHILD_ID_SELF: i32 = 0;
+
+/// Standard object identifiers, passed as `id_object` to an [`EventHandler`](crate::EventHandler).
+/// See [Object Identifiers](https://learn.microsoft.com/en-us/windows/win32/winauto/object-identifiers)
+/// for more information.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ObjectId {
+    /// The window itself rather than a child object.
+    Window,
+    /// The window's system menu.
+    SysMenu,
+    /// The window's title bar.
+    TitleBar,
+    /// The window's menu bar.
+    Menu,
+    /// The window's client area.
+    Client,
+    /// The window's vertical scroll bar.
+    VScroll,
+    /// The window's horizontal scroll bar.
+    HScroll,
+    /// The window's size grip.
+    SizeGrip,
+    /// The text insertion bar (caret) in the window.
+    Caret,
+    /// The mouse pointer.
+    Cursor,
+    /// An alert that is associated with a window or an application.
+    Alert,
+    /// A sound object.
+    Sound,
+    /// An object identifier used by `oleacc.dll` internally.
+    QueryClassNameIdx,
+    /// The native object model of the window.
+    NativeOm,
+    /// An application-defined object identifier.
+    Other(i32),
+}
+
+impl ObjectId {
+    /// Every standard object identifier.
+    pub const ALL: [ObjectId; 14] = [
+        ObjectId::Window,
+        ObjectId::SysMenu,
+        ObjectId::TitleBar,
+        ObjectId::Menu,
+        ObjectId::Client,
+        ObjectId::VScroll,
+        ObjectId::HScroll,
+        ObjectId::SizeGrip,
+        ObjectId::Caret,
+        ObjectId::Cursor,
+        ObjectId::Alert,
+        ObjectId::Sound,
+        ObjectId::QueryClassNameIdx,
+        ObjectId::NativeOm,
+    ];
+
+    /// The name of the identifier, as in the Windows SDK, for instance `OBJID_CLIENT`.
+    ///
+    /// Note: Application-defined identifiers have no name.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            ObjectId::Window => Some("OBJID_WINDOW"),
+            ObjectId::SysMenu => Some("OBJID_SYSMENU"),
+            ObjectId::TitleBar => Some("OBJID_TITLEBAR"),
+            ObjectId::Menu => Some("OBJID_MENU"),
+            ObjectId::Client => Some("OBJID_CLIENT"),
+            ObjectId::VScroll => Some("OBJID_VSCROLL"),
+            ObjectId::HScroll => Some("OBJID_HSCROLL"),
+            ObjectId::SizeGrip => Some("OBJID_SIZEGRIP"),
+            ObjectId::Caret => Some("OBJID_CARET"),
+            ObjectId::Cursor => Some("OBJID_CURSOR"),
+            ObjectId::Alert => Some("OBJID_ALERT"),
+            ObjectId::Sound => Some("OBJID_SOUND"),
+            ObjectId::QueryClassNameIdx => Some("OBJID_QUERYCLASSNAMEIDX"),
+            ObjectId::NativeOm => Some("OBJID_NATIVEOM"),
+            ObjectId::Other(_) => None,
+        }
+    }
+}
+
+impl From<i32> for ObjectId {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => ObjectId::Window,
+            -1 => ObjectId::SysMenu,
+            -2 => ObjectId::TitleBar,
+            -3 => ObjectId::Menu,
+            -4 => ObjectId::Client,
+            -5 => ObjectId::VScroll,
+            -6 => ObjectId::HScroll,
+            -7 => ObjectId::SizeGrip,
+            -8 => ObjectId::Caret,
+            -9 => ObjectId::Cursor,
+            -10 => ObjectId::Alert,
+            -11 => ObjectId::Sound,
+            -12 => ObjectId::QueryClassNameIdx,
+            -16 => ObjectId::NativeOm,
+            value => ObjectId::Other(value),
+        }
+    }
+}
+
+impl From<ObjectId> for i32 {
+    fn from(value: ObjectId) -> Self {
+        match value {
+            ObjectId::Window => 0,
+            ObjectId::SysMenu => -1,
+            ObjectId::TitleBar => -2,
+            ObjectId::Menu => -3,
+            ObjectId::Client => -4,
+            ObjectId::VScroll => -5,
+            ObjectId::HScroll => -6,
+            ObjectId::SizeGrip => -7,
+            ObjectId::Caret => -8,
+            ObjectId::Cursor => -9,
+            ObjectId::Alert => -10,
+            ObjectId::Sound => -11,
+            ObjectId::QueryClassNameIdx => -12,
+            ObjectId::NativeOm => -16,
+            ObjectId::Other(value) => value,
+        }
+    }
+}
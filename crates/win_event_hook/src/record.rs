@@ -0,0 +1,57 @@
+use std::time::Instant;
+
+use crate::{events::Event, handler::receipt, handles::WindowHandle};
+
+/// A single event, with the values passed to an [`EventHandler`](crate::EventHandler).
+///
+/// Note: Records can be stored and processed on any platform, see [`crate::capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    /// The event that occurred.
+    pub event: Event,
+    /// The window that generated the event, or a null handle if no window is associated with it.
+    pub hwnd: WindowHandle,
+    /// The object associated with the event.
+    pub id_object: i32,
+    /// Whether the event was triggered by an object or a child element of the object.
+    pub id_child: i32,
+    /// The thread that generated the event.
+    pub id_event_thread: u32,
+    /// The time, in milliseconds since the system started, at which the event was generated.
+    pub event_time: u32,
+    /// The monotonic time at which the hook received the event, if the record was created by an
+    /// [`EventHandler`](crate::EventHandler).
+    ///
+    /// Note: This isn't stored in captures, as it's only meaningful within the process that received the event.
+    pub received: Option<Instant>,
+    /// The position of the event among those delivered by its hook, if known.
+    ///
+    /// See [`Receipt::sequence`](crate::handler::Receipt::sequence).
+    pub sequence: Option<u64>,
+}
+
+impl EventRecord {
+    /// Returns a new [`EventRecord`], from the values passed to an [`EventHandler`](crate::EventHandler).
+    ///
+    /// Note: When called from an [`EventHandler`](crate::EventHandler), this includes the time the event was
+    /// received and its sequence number, see [`crate::handler::receipt`].
+    pub fn new(
+        event: Event,
+        hwnd: WindowHandle,
+        id_object: i32,
+        id_child: i32,
+        id_event_thread: u32,
+        event_time: u32,
+    ) -> Self {
+        Self {
+            event,
+            hwnd,
+            id_object,
+            id_child,
+            id_event_thread,
+            event_time,
+            received: receipt().map(|receipt| receipt.received),
+            sequence: receipt().map(|receipt| receipt.sequence),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+use arc_swap::ArcSwap;
+
+use crate::handles::OsHandle;
+
+/// Storage for hook data, keyed by [`OsHandle`].
+///
+/// Readers load an immutable snapshot of the storage, which never blocks. Writers build a new snapshot and
+/// atomically swap it in, so there is no lock that may be contended or poisoned.
+pub struct Registry<T> {
+    snapshot: ArcSwap<HashMap<OsHandle, Weak<T>>>,
+}
+
+impl<T> Registry<T> {
+    /// Returns a new, empty [`Registry`].
+    pub fn new() -> Self {
+        Self {
+            snapshot: ArcSwap::from_pointee(HashMap::new()),
+        }
+    }
+
+    /// Stores a weakref to the given data for a given [`OsHandle`], replacing any existing entry.
+    pub fn insert(&self, handle: OsHandle, data: &Arc<T>) {
+        let data = Arc::downgrade(data);
+
+        self.snapshot.rcu(|current| {
+            let mut next = HashMap::clone(current);
+            next.insert(handle.clone(), data.clone());
+            next
+        });
+    }
+
+    /// Removes the entry for a given [`OsHandle`], returning whether an entry existed.
+    pub fn remove(&self, handle: &OsHandle) -> bool {
+        let previous = self.snapshot.rcu(|current| {
+            let mut next = HashMap::clone(current);
+            next.remove(handle);
+            next
+        });
+
+        previous.contains_key(handle)
+    }
+
+    /// Obtains the weakref for a given [`OsHandle`], if an entry exists.
+    pub fn get(&self, handle: &OsHandle) -> Option<Weak<T>> {
+        self.snapshot.load().get(handle).cloned()
+    }
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
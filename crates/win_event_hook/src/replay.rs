@@ -0,0 +1,345 @@
+//! Replays recorded [`EventRecord`]s into an [`EventHandler`], as if they were raised by the os.
+//!
+//! Records can be obtained from a capture (see [`crate::capture`]) or from JSON Lines (see the `jsonl`
+//! module, behind the `jsonl` feature), on any platform. For instance:
+//!
+//! ```no_run
+//! # fn main() -> win_event_hook::errors::Result<()> {
+//! use std::{fs::File, io::BufReader};
+//!
+//! use win_event_hook::{capture::CaptureReader, replay::{Pacing, Replayer}};
+//!
+//! let reader = CaptureReader::new(BufReader::new(File::open("capture.bin")?))?;
+//! let config = reader.header().config.clone();
+//!
+//! let summary = Replayer::new(&config, reader.filter_map(Result::ok))
+//!     .with_pacing(Pacing::Speed(10.0))
+//!     .run(&|ev, _, _, _, _, _| println!("got event: {:?}", ev));
+//!
+//! println!("delivered {} events", summary.delivered);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::{error, trace};
+
+use crate::{
+    config::Config,
+    handler::{with_receipt, EventHandler, Receipt},
+    record::EventRecord,
+};
+
+/// Controls how quickly records are replayed, by [`Replayer::run`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Records are replayed with the same spacing they were recorded with.
+    Original,
+    /// Records are replayed with their original spacing, divided by a given multiplier. For
+    /// instance, `2.0` replays twice as fast as recorded.
+    Speed(f64),
+    /// Records are replayed as fast as possible.
+    AsFastAsPossible,
+}
+
+/// The outcome of replaying a single record, see [`Replayer::step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayStep {
+    /// The record was delivered to the handler.
+    Delivered(EventRecord),
+    /// The record was not accepted by the [`Config`], so was not delivered to the handler.
+    Filtered(EventRecord),
+}
+
+/// The outcome of replaying a number of records.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    /// The number of records delivered to the handler.
+    pub delivered: u64,
+    /// The number of records not accepted by the [`Config`].
+    pub filtered: u64,
+    /// The number of delivered records where the handler panicked.
+    pub handler_panics: u64,
+}
+
+/// Replays [`EventRecord`]s into an [`EventHandler`].
+///
+/// Records are filtered as they would be for a live hook using the same [`Config`]; see
+/// [`Config::accepts`] for details.
+///
+/// Note: As with a live hook, handler panics are caught and counted, and records with a sequence number are
+/// delivered with a [`Receipt`], see [`crate::handler::receipt`].
+pub struct Replayer<I: Iterator<Item = EventRecord>> {
+    records: I,
+    config: Config,
+    pacing: Pacing,
+    clock: Option<ReplayClock>,
+    summary: ReplaySummary,
+}
+
+/// Tracks the progress of a paced replay, against the original timing of the records.
+struct ReplayClock {
+    started: Instant,
+    last_event_time: u32,
+    elapsed: Duration,
+}
+
+impl<I: Iterator<Item = EventRecord>> Replayer<I> {
+    /// Returns a new [`Replayer`], filtering a given set of records with a given [`Config`].
+    ///
+    /// Note: Records are replayed using [`Pacing::Original`] by default.
+    pub fn new<T: IntoIterator<IntoIter = I>>(config: &Config, records: T) -> Self {
+        Self {
+            records: records.into_iter(),
+            config: config.clone(),
+            pacing: Pacing::Original,
+            clock: None,
+            summary: ReplaySummary::default(),
+        }
+    }
+
+    /// Sets the [`Pacing`] used by [`Self::run`].
+    pub fn with_pacing(self, pacing: Pacing) -> Self {
+        Self { pacing, ..self }
+    }
+
+    /// Replays the next record immediately, regardless of [`Pacing`], returning `None` once
+    /// all records are replayed.
+    ///
+    /// Note: This is useful for single-stepping through records, for instance while debugging a handler.
+    pub fn step<F: EventHandler + ?Sized>(&mut self, handler: &F) -> Option<ReplayStep> {
+        let record = self.records.next()?;
+
+        Some(self.deliver(record, handler))
+    }
+
+    /// Replays all remaining records, using the configured [`Pacing`].
+    pub fn run<F: EventHandler + ?Sized>(&mut self, handler: &F) -> ReplaySummary {
+        while let Some(record) = self.records.next() {
+            self.wait_for(&record);
+            self.deliver(record, handler);
+        }
+
+        self.summary()
+    }
+
+    /// The records replayed so far.
+    pub fn summary(&self) -> ReplaySummary {
+        self.summary.clone()
+    }
+
+    fn deliver<F: EventHandler + ?Sized>(
+        &mut self,
+        record: EventRecord,
+        handler: &F,
+    ) -> ReplayStep {
+        if !self.config.accepts(&record.event, record.id_event_thread) {
+            trace!(?record, "replayed event filtered");
+
+            self.summary.filtered += 1;
+
+            return ReplayStep::Filtered(record);
+        }
+
+        let call = || {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                handler(
+                    record.event,
+                    record.hwnd.clone(),
+                    record.id_object,
+                    record.id_child,
+                    record.id_event_thread,
+                    record.event_time,
+                )
+            }))
+        };
+
+        let result = match record.sequence {
+            Some(sequence) => {
+                let receipt = Receipt {
+                    received: record.received.unwrap_or_else(Instant::now),
+                    sequence,
+                };
+
+                with_receipt(receipt, call)
+            }
+            None => call(),
+        };
+
+        self.summary.delivered += 1;
+
+        if result.is_err() {
+            self.summary.handler_panics += 1;
+
+            error!(?record, "event handler panicked during replay");
+        }
+
+        ReplayStep::Delivered(record)
+    }
+
+    /// Waits until a given record is due, according to the configured [`Pacing`].
+    fn wait_for(&mut self, record: &EventRecord) {
+        let speed = match self.pacing {
+            Pacing::Original => 1.0,
+            Pacing::Speed(speed) if speed > 0.0 && speed.is_finite() => speed,
+            _ => return,
+        };
+
+        let clock = self.clock.get_or_insert(ReplayClock {
+            started: Instant::now(),
+            last_event_time: record.event_time,
+            elapsed: Duration::ZERO,
+        });
+
+        // event times are milliseconds since the system started, wrapping every ~49.7 days. Times
+        // that appear to go backwards (as recorded events may be slightly out of order) don't wait.
+        let delta = record.event_time.wrapping_sub(clock.last_event_time);
+        if delta <= i32::MAX as u32 {
+            clock.elapsed += Duration::from_millis(delta as u64);
+            clock.last_event_time = record.event_time;
+        }
+
+        let due = clock.started + clock.elapsed.div_f64(speed);
+        let now = Instant::now();
+
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    };
+
+    use super::{Pacing, ReplayStep, Replayer};
+    use crate::{
+        config::Config,
+        events::{Event, NamedEvent},
+        handler::receipt,
+        handles::WindowHandle,
+        record::EventRecord,
+    };
+
+    fn record(event: NamedEvent, thread: u32, time: u32) -> EventRecord {
+        EventRecord::new(
+            Event::Named(event),
+            WindowHandle::default(),
+            0,
+            0,
+            thread,
+            time,
+        )
+    }
+
+    #[test]
+    fn replay_applies_config_filtering() {
+        let config = Config::builder()
+            .with_event(Event::Named(NamedEvent::ObjectShow))
+            .with_event(Event::Named(NamedEvent::ObjectHide))
+            .with_thread_id(7)
+            .finish();
+        let records = vec![
+            record(NamedEvent::ObjectShow, 7, 0),
+            record(NamedEvent::ObjectShow, 8, 0),
+            record(NamedEvent::ObjectNameChange, 7, 0),
+            record(NamedEvent::ObjectHide, 7, 0),
+            record(NamedEvent::SystemForeground, 7, 0),
+        ];
+
+        let delivered = Mutex::new(Vec::new());
+        let summary = Replayer::new(&config, records)
+            .with_pacing(Pacing::AsFastAsPossible)
+            .run(&|ev, _, _, _, _, _| delivered.lock().unwrap().push(ev));
+
+        assert_eq!(
+            *delivered.lock().unwrap(),
+            vec![
+                Event::Named(NamedEvent::ObjectShow),
+                Event::Named(NamedEvent::ObjectHide)
+            ]
+        );
+        assert_eq!((summary.delivered, summary.filtered), (2, 3));
+    }
+
+    #[test]
+    fn replay_steps_one_record_at_a_time() {
+        let config = Config::builder()
+            .with_event(Event::Named(NamedEvent::ObjectShow))
+            .finish();
+        let mut replayer = Replayer::new(
+            &config,
+            vec![
+                record(NamedEvent::ObjectShow, 1, 0),
+                record(NamedEvent::ObjectHide, 1, 60_000),
+            ],
+        );
+        let handler = |_, _, _, _, _, _| {};
+
+        assert!(matches!(
+            replayer.step(&handler),
+            Some(ReplayStep::Delivered(_))
+        ));
+        assert!(matches!(
+            replayer.step(&handler),
+            Some(ReplayStep::Filtered(_))
+        ));
+        assert_eq!(replayer.step(&handler), None);
+    }
+
+    #[test]
+    fn replay_counts_panics_and_provides_receipts() {
+        let records = vec![
+            EventRecord {
+                sequence: Some(7),
+                ..record(NamedEvent::ObjectShow, 1, 0)
+            },
+            record(NamedEvent::ObjectHide, 1, 0),
+            record(NamedEvent::ObjectShow, 1, 0),
+        ];
+
+        let sequences = Mutex::new(Vec::new());
+        let summary = Replayer::new(&Config::default(), records)
+            .with_pacing(Pacing::AsFastAsPossible)
+            .run(&|ev, _, _, _, _, _| {
+                sequences
+                    .lock()
+                    .unwrap()
+                    .push(receipt().map(|receipt| receipt.sequence));
+
+                if ev == Event::Named(NamedEvent::ObjectHide) {
+                    panic!("handler panic");
+                }
+            });
+
+        assert_eq!(*sequences.lock().unwrap(), vec![Some(7), None, None]);
+        assert_eq!((summary.delivered, summary.handler_panics), (3, 1));
+    }
+
+    #[test]
+    fn replay_scales_original_timing() {
+        // spans the tick count wrapping around
+        let records = vec![
+            record(NamedEvent::ObjectShow, 1, u32::MAX - 100),
+            record(NamedEvent::ObjectShow, 1, 99),
+            record(NamedEvent::ObjectShow, 1, 299),
+        ];
+
+        let started = Instant::now();
+        let summary = Replayer::new(&Config::default(), records)
+            .with_pacing(Pacing::Speed(4.0))
+            .run(&|_, _, _, _, _, _| {});
+        let elapsed = started.elapsed();
+
+        assert_eq!(summary.delivered, 3);
+        assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,328 @@
+//! Sampling and rate limiting of high-volume events.
+//!
+//! A [`Sampler`] decides which events to keep, using a [`SamplePolicy`] chosen by [`Event`] or
+//! [`EventCategory`]. Each kept event has a weight; the number of events it stands in for (itself,
+//! and those dropped since the last kept event of the same type). Summing weights, rather than counting
+//! kept events, keeps aggregate counts correct.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::{
+    errors::{Error, Result},
+    events::{Event, EventCategory},
+    handler::{EventHandler, SampledHandler},
+    record::EventRecord,
+};
+
+/// How a [`Sampler`] decides which events of a type to keep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplePolicy {
+    /// Every event is kept.
+    KeepAll,
+    /// The first of every `n` events is kept.
+    ///
+    /// Note: Sampling is by count, rather than random, so weights are exact.
+    OneIn(u32),
+    /// Events are kept at up to `rate` per second, with bursts of up to `burst` events.
+    ///
+    /// Note: `rate` must be positive and `burst` at least `1.0`, otherwise no events could be kept.
+    TokenBucket { rate: f64, burst: f64 },
+}
+
+impl SamplePolicy {
+    /// Returns `true` if events can be kept under this policy.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Self::KeepAll => true,
+            Self::OneIn(n) => n > 0,
+            // comparisons with NaN are false, so it's rejected too
+            Self::TokenBucket { rate, burst } => rate > 0.0 && rate.is_finite() && burst >= 1.0,
+        }
+    }
+
+    /// Returns `self` if it's valid, otherwise [`Error::InvalidSamplePolicy`].
+    fn validate(self) -> Result<Self> {
+        match self.is_valid() {
+            true => Ok(self),
+            false => Err(Error::InvalidSamplePolicy(self)),
+        }
+    }
+}
+
+/// Counts of the events a [`Sampler`] has seen, for a single [`Event`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SampleCounts {
+    /// Number of events seen.
+    pub seen: u64,
+    /// Number of events kept.
+    pub kept: u64,
+    /// Number of events dropped since the last kept event, which are not yet represented by a weight.
+    pub unrepresented: u64,
+}
+
+/// The sampling state of a single [`Event`].
+#[derive(Debug)]
+struct EventState {
+    counts: SampleCounts,
+    tokens: f64,
+    refilled: Option<Instant>,
+}
+
+/// Decides which events to keep, by [`Event`] or [`EventCategory`].
+///
+/// Note: Each event type is sampled independently, so a category policy applies to each of its events separately.
+#[derive(Debug)]
+pub struct Sampler {
+    default: SamplePolicy,
+    events: HashMap<Event, SamplePolicy>,
+    categories: HashMap<EventCategory, SamplePolicy>,
+    state: HashMap<Event, EventState>,
+}
+
+impl Sampler {
+    /// Returns a new [`Sampler`], using a given policy for events without a more specific one.
+    ///
+    /// Note: Returns [`Error::InvalidSamplePolicy`] if the policy is not valid, see [`SamplePolicy::is_valid`].
+    pub fn new(default: SamplePolicy) -> Result<Self> {
+        Ok(Self {
+            default: default.validate()?,
+            events: HashMap::new(),
+            categories: HashMap::new(),
+            state: HashMap::new(),
+        })
+    }
+
+    /// Sets the policy for a particular [`Event`].
+    ///
+    /// Note: This takes precedence over the policy of the event's category.
+    /// Returns [`Error::InvalidSamplePolicy`] if the policy is not valid, see [`SamplePolicy::is_valid`].
+    pub fn with_event(mut self, event: Event, policy: SamplePolicy) -> Result<Self> {
+        self.events.insert(event, policy.validate()?);
+        Ok(self)
+    }
+
+    /// Sets the policy for the events of a particular [`EventCategory`].
+    ///
+    /// Note: Returns [`Error::InvalidSamplePolicy`] if the policy is not valid, see [`SamplePolicy::is_valid`].
+    pub fn with_category(mut self, category: EventCategory, policy: SamplePolicy) -> Result<Self> {
+        self.categories.insert(category, policy.validate()?);
+        Ok(self)
+    }
+
+    /// The policy used for a given [`Event`].
+    pub fn policy(&self, event: Event) -> SamplePolicy {
+        self.events
+            .get(&event)
+            .or_else(|| self.categories.get(&event.category()))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Decides if an event, seen at a given time, is kept, returning its weight if so.
+    pub fn sample(&mut self, event: Event, now: Instant) -> Option<u64> {
+        let policy = self.policy(event);
+        let state = self.state.entry(event).or_insert_with(|| EventState {
+            counts: SampleCounts::default(),
+            tokens: 0.0,
+            refilled: None,
+        });
+
+        state.counts.seen += 1;
+
+        let keep = match policy {
+            SamplePolicy::KeepAll => true,
+            SamplePolicy::OneIn(n) => (state.counts.seen - 1).is_multiple_of(u64::from(n)),
+            SamplePolicy::TokenBucket { rate, burst } => {
+                // buckets start full, so the first events of a type are always kept
+                state.tokens = match state.refilled {
+                    Some(refilled) => {
+                        let elapsed = now.saturating_duration_since(refilled).as_secs_f64();
+
+                        (state.tokens + elapsed * rate).min(burst)
+                    }
+                    None => burst,
+                };
+                state.refilled = Some(now);
+
+                match state.tokens >= 1.0 {
+                    true => {
+                        state.tokens -= 1.0;
+                        true
+                    }
+                    false => false,
+                }
+            }
+        };
+
+        if !keep {
+            state.counts.unrepresented += 1;
+
+            return None;
+        }
+
+        let weight = state.counts.unrepresented + 1;
+        state.counts.kept += 1;
+        state.counts.unrepresented = 0;
+
+        Some(weight)
+    }
+
+    /// Decides if a given [`EventRecord`] is kept, returning its weight if so.
+    ///
+    /// Note: Records are sampled at the time they were received, if known, and otherwise now.
+    pub fn sample_record(&mut self, record: &EventRecord) -> Option<u64> {
+        self.sample(record.event, record.received.unwrap_or_else(Instant::now))
+    }
+
+    /// The counts of each [`Event`] seen.
+    pub fn counts(&self) -> HashMap<Event, SampleCounts> {
+        self.state
+            .iter()
+            .map(|(event, state)| (*event, state.counts))
+            .collect()
+    }
+
+    /// Returns an [`EventHandler`] that passes the events kept by a given [`Sampler`] to a [`SampledHandler`],
+    /// with their weights.
+    pub fn handler<F: SampledHandler + 'static>(
+        sampler: &Arc<Mutex<Self>>,
+        handler: F,
+    ) -> impl EventHandler {
+        let sampler = sampler.clone();
+
+        move |event, hwnd, id_object, id_child, id_event_thread, event_time| {
+            let record = EventRecord::new(
+                event,
+                hwnd,
+                id_object,
+                id_child,
+                id_event_thread,
+                event_time,
+            );
+
+            let weight = sampler
+                .lock()
+                .expect("Unable to obtain sampler lock")
+                .sample_record(&record);
+
+            // the lock is released first, so slow handlers don't hold up sampling on other threads
+            if let Some(weight) = weight {
+                handler(&record, weight);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SamplePolicy, Sampler};
+    use crate::{
+        errors::Error,
+        events::{Event, EventCategory, NamedEvent},
+    };
+
+    const LOCATION_CHANGE: Event = Event::Named(NamedEvent::ObjectLocationChange);
+    const NAME_CHANGE: Event = Event::Named(NamedEvent::ObjectNameChange);
+    const FOREGROUND: Event = Event::Named(NamedEvent::SystemForeground);
+
+    #[test]
+    fn one_in_n_weights_sum_to_seen() {
+        let now = Instant::now();
+        let mut sampler = Sampler::new(SamplePolicy::KeepAll)
+            .and_then(|sampler| sampler.with_category(EventCategory::Named, SamplePolicy::OneIn(4)))
+            .and_then(|sampler| sampler.with_event(FOREGROUND, SamplePolicy::KeepAll))
+            .expect("policies are valid");
+
+        let weights = (0..10)
+            .filter_map(|_| sampler.sample(LOCATION_CHANGE, now))
+            .collect::<Vec<_>>();
+        assert_eq!(weights, vec![1, 4, 4]);
+
+        assert_eq!(sampler.sample(FOREGROUND, now), Some(1));
+        assert_eq!(sampler.sample(FOREGROUND, now), Some(1));
+
+        let counts = sampler.counts()[&LOCATION_CHANGE];
+        assert_eq!((counts.seen, counts.kept, counts.unrepresented), (10, 3, 1));
+        assert_eq!(weights.iter().sum::<u64>() + counts.unrepresented, 10);
+    }
+
+    #[test]
+    fn token_bucket_limits_rate() {
+        let start = Instant::now();
+        let mut sampler = Sampler::new(SamplePolicy::KeepAll)
+            .and_then(|sampler| {
+                sampler.with_event(
+                    NAME_CHANGE,
+                    SamplePolicy::TokenBucket {
+                        rate: 10.0,
+                        burst: 2.0,
+                    },
+                )
+            })
+            .expect("policies are valid");
+
+        // a burst of 2 is kept, then the bucket is empty
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(sampler.sample(NAME_CHANGE, at(0)), Some(1));
+        assert_eq!(sampler.sample(NAME_CHANGE, at(0)), Some(1));
+        assert_eq!(sampler.sample(NAME_CHANGE, at(10)), None);
+        assert_eq!(sampler.sample(NAME_CHANGE, at(50)), None);
+
+        // a token is added every 100ms
+        assert_eq!(sampler.sample(NAME_CHANGE, at(100)), Some(3));
+        assert_eq!(sampler.sample(NAME_CHANGE, at(150)), None);
+
+        // other events are unaffected
+        assert_eq!(sampler.sample(LOCATION_CHANGE, at(150)), Some(1));
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        let invalid = [
+            SamplePolicy::OneIn(0),
+            SamplePolicy::TokenBucket {
+                rate: 10.0,
+                burst: 0.5,
+            },
+            SamplePolicy::TokenBucket {
+                rate: 0.0,
+                burst: 2.0,
+            },
+            SamplePolicy::TokenBucket {
+                rate: -1.0,
+                burst: 2.0,
+            },
+            SamplePolicy::TokenBucket {
+                rate: f64::NAN,
+                burst: 2.0,
+            },
+            SamplePolicy::TokenBucket {
+                rate: 10.0,
+                burst: f64::NAN,
+            },
+        ];
+
+        for policy in invalid {
+            assert!(!policy.is_valid(), "{policy:?}");
+            assert!(matches!(
+                Sampler::new(policy),
+                Err(Error::InvalidSamplePolicy(_))
+            ));
+            assert!(matches!(
+                Sampler::new(SamplePolicy::KeepAll).and_then(|s| s.with_event(NAME_CHANGE, policy)),
+                Err(Error::InvalidSamplePolicy(_))
+            ));
+            assert!(matches!(
+                Sampler::new(SamplePolicy::KeepAll)
+                    .and_then(|s| s.with_category(EventCategory::Named, policy)),
+                Err(Error::InvalidSamplePolicy(_))
+            ));
+        }
+    }
+}
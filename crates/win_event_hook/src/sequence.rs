@@ -0,0 +1,275 @@
+//! Detection of lost events, using the sequence numbers assigned as events are delivered.
+//!
+//! Each hook numbers the events it delivers to its handler, see
+//! [`Receipt::sequence`](crate::handler::Receipt::sequence). Those numbers are carried by each
+//! [`EventRecord`] (and stored in captures and JSON Lines), so a [`GapDetector`] downstream can report
+//! events that were lost between the handler and it.
+//!
+//! Sharded hooks (see [`crate::handoff`]) deliver events from several workers at once, so their sequence
+//! numbers arrive out of order. A reorder window (see [`GapDetector::with_reorder_window`]) lets late
+//! numbers fill the gaps they left, rather than being reported as lost.
+
+use std::{collections::BTreeMap, fmt};
+
+use tracing::debug;
+
+use crate::record::EventRecord;
+
+/// A range of sequence numbers that were never observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gap {
+    /// The first missing sequence number.
+    pub first: u64,
+    /// The last missing sequence number, which may be the same as `first`.
+    pub last: u64,
+}
+
+impl Gap {
+    /// The number of missing sequence numbers.
+    pub fn missing(&self) -> u64 {
+        self.last - self.first + 1
+    }
+}
+
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.first == self.last {
+            true => write!(f, "{}", self.first),
+            false => write!(f, "{}..={}", self.first, self.last),
+        }
+    }
+}
+
+/// Reports [`Gap`]s in a stream of sequence numbers from a single hook.
+///
+/// Note: Sequence numbers that arrive after a later one are counted as out of order. Those that arrive within
+/// the reorder window fill their gap; those that arrive after it are not removed from gaps already reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GapDetector {
+    next: Option<u64>,
+    reorder: u64,
+    /// Gaps that may still be filled by late sequence numbers, as `first` to `last`.
+    open: BTreeMap<u64, u64>,
+    missing: u64,
+    gaps: u64,
+    out_of_order: u64,
+}
+
+impl GapDetector {
+    /// Returns a new [`GapDetector`], expecting the stream to continue from the first sequence number observed.
+    ///
+    /// Note: This suits consumers that join a stream part way through, for instance socket clients.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new [`GapDetector`], expecting the stream to begin with a given sequence number.
+    ///
+    /// Note: Use `0` for a complete stream from a hook, for instance a capture.
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            next: Some(first),
+            ..Self::default()
+        }
+    }
+
+    /// Allows sequence numbers to arrive up to a given distance behind the latest one, before they're
+    /// reported as missing.
+    ///
+    /// Note: Use `u64::MAX` for a complete stream, and call [`Self::finish`] at its end.
+    pub fn with_reorder_window(self, reorder: u64) -> Self {
+        Self { reorder, ..self }
+    }
+
+    /// Observes a given sequence number, returning the [`Gap`]s that are now beyond the reorder window.
+    pub fn observe(&mut self, sequence: u64) -> Vec<Gap> {
+        let next = *self.next.get_or_insert(sequence);
+
+        if sequence < next {
+            self.out_of_order += 1;
+            self.fill(sequence);
+
+            return Vec::new();
+        }
+
+        if sequence > next {
+            self.open.insert(next, sequence - 1);
+        }
+
+        self.next = Some(sequence + 1);
+
+        self.expire(sequence.saturating_sub(self.reorder))
+    }
+
+    /// Observes the sequence number of a given [`EventRecord`], returning the [`Gap`]s that are now beyond
+    /// the reorder window.
+    ///
+    /// Note: Records without a sequence number are ignored.
+    pub fn observe_record(&mut self, record: &EventRecord) -> Vec<Gap> {
+        match record.sequence {
+            Some(sequence) => self.observe(sequence),
+            None => Vec::new(),
+        }
+    }
+
+    /// Ends the stream, returning the [`Gap`]s still within the reorder window.
+    pub fn finish(&mut self) -> Vec<Gap> {
+        self.expire(u64::MAX)
+    }
+
+    /// The number of sequence numbers missing, across all gaps reported.
+    pub fn missing(&self) -> u64 {
+        self.missing
+    }
+
+    /// The number of gaps reported.
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// The number of sequence numbers within the reorder window that haven't been observed yet.
+    pub fn pending(&self) -> u64 {
+        self.open.iter().map(|(first, last)| last - first + 1).sum()
+    }
+
+    /// The number of sequence numbers observed after a later one.
+    pub fn out_of_order(&self) -> u64 {
+        self.out_of_order
+    }
+
+    /// The next sequence number expected, if any have been observed.
+    pub fn next(&self) -> Option<u64> {
+        self.next
+    }
+
+    /// Removes a late sequence number from the open gap containing it, if any.
+    fn fill(&mut self, sequence: u64) {
+        let Some((&first, &last)) = self.open.range(..=sequence).next_back() else {
+            return;
+        };
+
+        if last < sequence {
+            return;
+        }
+
+        self.open.remove(&first);
+
+        if first < sequence {
+            self.open.insert(first, sequence - 1);
+        }
+
+        if sequence < last {
+            self.open.insert(sequence + 1, last);
+        }
+    }
+
+    /// Reports the open sequence numbers below a given bound as missing.
+    fn expire(&mut self, bound: u64) -> Vec<Gap> {
+        let mut expired = Vec::new();
+
+        while let Some(entry) = self.open.first_entry() {
+            let (first, last) = (*entry.key(), *entry.get());
+
+            if first >= bound {
+                break;
+            }
+
+            entry.remove();
+
+            let gap = Gap {
+                first,
+                last: last.min(bound - 1),
+            };
+
+            if gap.last < last {
+                self.open.insert(bound, last);
+            }
+
+            self.missing += gap.missing();
+            self.gaps += 1;
+
+            debug!(%gap, "events missing from sequence");
+
+            expired.push(gap);
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gap, GapDetector};
+
+    #[test]
+    fn reports_missing_ranges() {
+        let mut detector = GapDetector::starting_at(0);
+
+        assert_eq!(detector.observe(0), vec![]);
+        assert_eq!(detector.observe(1), vec![]);
+        assert_eq!(detector.observe(4), vec![Gap { first: 2, last: 3 }]);
+        assert_eq!(detector.observe(6), vec![Gap { first: 5, last: 5 }]);
+        assert_eq!(detector.observe(3), vec![]);
+        assert_eq!(detector.observe(7), vec![]);
+
+        assert_eq!(detector.missing(), 3);
+        assert_eq!(detector.gaps(), 2);
+        assert_eq!(detector.out_of_order(), 1);
+        assert_eq!(detector.next(), Some(8));
+        assert_eq!(Gap { first: 2, last: 3 }.to_string(), "2..=3");
+    }
+
+    #[test]
+    fn joins_part_way_through() {
+        let mut detector = GapDetector::new();
+
+        assert_eq!(detector.observe(100), vec![]);
+        assert_eq!(
+            detector.observe(102),
+            vec![Gap {
+                first: 101,
+                last: 101
+            }]
+        );
+        assert_eq!(detector.missing(), 1);
+
+        assert_eq!(
+            GapDetector::starting_at(0).observe(100),
+            vec![Gap { first: 0, last: 99 }]
+        );
+    }
+
+    #[test]
+    fn reconciles_late_arrivals_within_window() {
+        let mut detector = GapDetector::starting_at(0).with_reorder_window(4);
+
+        assert_eq!(detector.observe(1), vec![]);
+        assert_eq!(detector.observe(3), vec![]);
+        assert_eq!(detector.pending(), 2);
+
+        // 0 and 2 arrive late, but within the window
+        assert_eq!(detector.observe(0), vec![]);
+        assert_eq!(detector.observe(2), vec![]);
+        assert_eq!(detector.pending(), 0);
+
+        // 4 and 6 are never observed, 5 arrives after the window has passed it
+        assert_eq!(detector.observe(7), vec![]);
+        assert_eq!(detector.observe(9), vec![Gap { first: 4, last: 4 }]);
+        assert_eq!(detector.observe(12), vec![Gap { first: 5, last: 6 }]);
+        assert_eq!(detector.observe(5), vec![]);
+
+        assert_eq!(
+            detector.finish(),
+            vec![
+                Gap { first: 8, last: 8 },
+                Gap {
+                    first: 10,
+                    last: 11
+                }
+            ]
+        );
+        assert_eq!(detector.missing(), 6);
+        assert_eq!(detector.gaps(), 4);
+        assert_eq!(detector.out_of_order(), 3);
+    }
+}
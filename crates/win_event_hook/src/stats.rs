@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use arc_swap::ArcSwap;
+
+use crate::events::Event;
+
+/// The number of buckets in a [`LatencyHistogram`].
+const LATENCY_BUCKETS: usize = 40;
+
+/// Runtime statistics for a hook, broken down by [`Event`].
+///
+/// See [`WinEventHook::stats`](crate::WinEventHook::stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Statistics for each [`Event`] received by the hook.
+    pub events: HashMap<Event, EventStats>,
+    /// The time at which these statistics began; when the hook was installed, or when they were last reset.
+    pub since: SystemTime,
+}
+
+impl Stats {
+    /// Combined statistics for all events received by the hook.
+    pub fn total(&self) -> EventStats {
+        self.events
+            .values()
+            .fold(EventStats::default(), |mut total, stats| {
+                total.merge(stats);
+                total
+            })
+    }
+
+    /// The time of the most recent event received by the hook, if any.
+    pub fn last_event(&self) -> Option<SystemTime> {
+        self.events
+            .values()
+            .filter_map(|stats| stats.last_event)
+            .max()
+    }
+}
+
+/// Runtime statistics for a single [`Event`] received by a hook.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventStats {
+    /// Number of events received by the hook.
+    pub received: u64,
+    /// Number of received events discarded by the hook's `event_filter`.
+    pub filtered: u64,
+    /// Number of received events delivered to the hook's handler, including those where the handler panicked.
+    pub delivered: u64,
+    /// Number of delivered events where the hook's handler panicked.
+    pub handler_panics: u64,
+    /// Number of received events dropped by the hook's handoff queue, as it was full.
+    ///
+    /// Note: Dropped events were numbered, so they appear as gaps in the sequence numbers delivered.
+    pub dropped: u64,
+    /// The time taken by the hook's handler, for delivered events.
+    pub latency: LatencyHistogram,
+    /// The time of the most recent event received by the hook, if any.
+    pub last_event: Option<SystemTime>,
+}
+
+impl EventStats {
+    /// Combines the given [`EventStats`] into these.
+    pub fn merge(&mut self, other: &EventStats) {
+        self.received += other.received;
+        self.filtered += other.filtered;
+        self.delivered += other.delivered;
+        self.handler_panics += other.handler_panics;
+        self.dropped += other.dropped;
+        self.latency.merge(&other.latency);
+        self.last_event = self.last_event.max(other.last_event);
+    }
+}
+
+/// A histogram of latencies, using buckets that double in size.
+///
+/// The bucket at index `i` counts latencies less than `2^(i + 1)` nanoseconds, and at least `2^i` nanoseconds.
+/// As a result, percentiles are accurate to within a factor of two. The last bucket is unbounded, so it also
+/// counts every longer latency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS],
+    sum_nanos: u64,
+}
+
+impl LatencyHistogram {
+    /// The number of latencies recorded.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The sum of all latencies recorded.
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos)
+    }
+
+    /// The mean of all latencies recorded, if any.
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(Duration::from_nanos(self.sum_nanos / count)),
+        }
+    }
+
+    /// The upper bound of the bucket containing the given quantile (from `0.0` to `1.0`), if any latencies
+    /// were recorded.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        self.buckets().find_map(|(upper_bound, bucket_count)| {
+            seen += bucket_count;
+
+            (seen >= rank).then_some(upper_bound)
+        })
+    }
+
+    /// The median latency. See [`Self::quantile`].
+    pub fn p50(&self) -> Option<Duration> {
+        self.quantile(0.5)
+    }
+
+    /// The 90th percentile latency. See [`Self::quantile`].
+    pub fn p90(&self) -> Option<Duration> {
+        self.quantile(0.9)
+    }
+
+    /// The 99th percentile latency. See [`Self::quantile`].
+    pub fn p99(&self) -> Option<Duration> {
+        self.quantile(0.99)
+    }
+
+    /// The upper bound, and count, of each bucket in the histogram, in ascending order.
+    ///
+    /// Note: Counts are per-bucket, not cumulative. The upper bound of the last bucket is [`Duration::MAX`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(index, count)| (bucket_upper_bound(index), *count))
+    }
+
+    /// Records a given latency.
+    pub fn record(&mut self, latency: Duration) {
+        self.counts[bucket_index(latency)] += 1;
+        self.sum_nanos = self.sum_nanos.saturating_add(duration_nanos(latency));
+    }
+
+    /// Combines the given [`LatencyHistogram`] into this one.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+
+        self.sum_nanos = self.sum_nanos.saturating_add(other.sum_nanos);
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BUCKETS],
+            sum_nanos: 0,
+        }
+    }
+}
+
+/// Live statistics for a hook, updated as events are dispatched.
+pub(crate) struct HookStats {
+    collection: ArcSwap<Collection>,
+}
+
+/// The counters collected since a [`HookStats`] was created or reset.
+#[derive(Clone)]
+struct Collection {
+    since: SystemTime,
+    events: HashMap<Event, Arc<EventCounters>>,
+}
+
+impl Collection {
+    fn new() -> Self {
+        Self {
+            since: SystemTime::now(),
+            events: HashMap::new(),
+        }
+    }
+
+    fn snapshot(&self) -> Stats {
+        Stats {
+            events: self
+                .events
+                .iter()
+                .map(|(event, counters)| (*event, counters.snapshot()))
+                .collect(),
+            since: self.since,
+        }
+    }
+}
+
+impl HookStats {
+    /// Returns a new [`HookStats`], with no events recorded.
+    pub fn new() -> Self {
+        Self {
+            collection: ArcSwap::from_pointee(Collection::new()),
+        }
+    }
+
+    /// Obtains the counters for a given [`Event`], creating them if needed.
+    ///
+    /// Note: Creation is the only time an update is made to the underlying storage, so that
+    /// events seen previously are counted without contention.
+    pub fn counters(&self, event: Event) -> Arc<EventCounters> {
+        loop {
+            if let Some(counters) = self.collection.load().events.get(&event) {
+                return counters.clone();
+            }
+
+            self.collection.rcu(|current| {
+                let mut next = Collection::clone(current);
+                next.events.entry(event).or_default();
+                next
+            });
+        }
+    }
+
+    /// Obtains a [`Stats`] snapshot of the current counters.
+    pub fn snapshot(&self) -> Stats {
+        self.collection.load().snapshot()
+    }
+
+    /// Resets the counters, returning a [`Stats`] snapshot of the values prior to the reset.
+    ///
+    /// Note: Dispatches in flight during the reset may still hold the previous counters, so their
+    /// increments are in neither the returned snapshot nor the new counters.
+    pub fn reset(&self) -> Stats {
+        self.collection.swap(Arc::new(Collection::new())).snapshot()
+    }
+}
+
+/// Live counters for a single [`Event`], updated as events are dispatched.
+pub(crate) struct EventCounters {
+    received: AtomicU64,
+    filtered: AtomicU64,
+    delivered: AtomicU64,
+    handler_panics: AtomicU64,
+    dropped: AtomicU64,
+    latency_counts: [AtomicU64; LATENCY_BUCKETS],
+    latency_sum_nanos: AtomicU64,
+    /// Nanoseconds since [`UNIX_EPOCH`] of the most recent event, or `0` if there is none.
+    last_event_nanos: AtomicU64,
+}
+
+impl EventCounters {
+    /// Records an event received by the hook.
+    pub fn on_received(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.last_event_nanos
+            .fetch_max(duration_nanos(now), Ordering::Relaxed);
+    }
+
+    /// Records an event discarded by the hook's event filter.
+    pub fn on_filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an event delivered to the hook's handler, and the time the handler took.
+    pub fn on_delivered(&self, latency: Duration, panicked: bool) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+
+        if panicked {
+            self.handler_panics.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.latency_counts[bucket_index(latency)].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_nanos
+            .fetch_add(duration_nanos(latency), Ordering::Relaxed);
+    }
+
+    /// Records an event dropped by the hook's handoff queue.
+    pub fn on_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> EventStats {
+        let last_event_nanos = self.last_event_nanos.load(Ordering::Relaxed);
+
+        EventStats {
+            received: self.received.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            handler_panics: self.handler_panics.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            latency: LatencyHistogram {
+                counts: std::array::from_fn(|index| {
+                    self.latency_counts[index].load(Ordering::Relaxed)
+                }),
+                sum_nanos: self.latency_sum_nanos.load(Ordering::Relaxed),
+            },
+            last_event: match last_event_nanos {
+                0 => None,
+                nanos => Some(UNIX_EPOCH + Duration::from_nanos(nanos)),
+            },
+        }
+    }
+}
+
+impl Default for EventCounters {
+    fn default() -> Self {
+        Self {
+            received: AtomicU64::new(0),
+            filtered: AtomicU64::new(0),
+            delivered: AtomicU64::new(0),
+            handler_panics: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            latency_counts: std::array::from_fn(|_| AtomicU64::new(0)),
+            latency_sum_nanos: AtomicU64::new(0),
+            last_event_nanos: AtomicU64::new(0),
+        }
+    }
+}
+
+/// The index of the [`LatencyHistogram`] bucket containing a given latency.
+fn bucket_index(latency: Duration) -> usize {
+    let nanos = duration_nanos(latency).max(1);
+
+    (nanos.ilog2() as usize).min(LATENCY_BUCKETS - 1)
+}
+
+/// The (exclusive) upper bound of the [`LatencyHistogram`] bucket with a given index.
+fn bucket_upper_bound(index: usize) -> Duration {
+    // longer latencies are clamped into the last bucket, so it has no upper bound
+    match index {
+        index if index >= LATENCY_BUCKETS - 1 => Duration::MAX,
+        index => Duration::from_nanos(1 << (index + 1)),
+    }
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{HookStats, LatencyHistogram};
+    use crate::events::{Event, NamedEvent};
+
+    #[test]
+    fn latency_histogram_quantiles() {
+        let mut histogram = LatencyHistogram::default();
+
+        assert_eq!(histogram.p50(), None);
+
+        for _ in 0..90 {
+            histogram.record(Duration::from_nanos(100));
+        }
+        for _ in 0..10 {
+            histogram.record(Duration::from_micros(100));
+        }
+
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.p50(), Some(Duration::from_nanos(128)));
+        assert_eq!(histogram.p90(), Some(Duration::from_nanos(128)));
+        assert_eq!(histogram.p99(), Some(Duration::from_nanos(131072)));
+        assert_eq!(histogram.mean(), Some(Duration::from_nanos(10090)));
+
+        // latencies beyond the largest bounded bucket are counted in the unbounded one
+        histogram.record(Duration::from_secs(3600));
+
+        let (upper_bound, count) = histogram.buckets().last().unwrap();
+        assert_eq!((upper_bound, count), (Duration::MAX, 1));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn hook_stats_reset() {
+        let stats = HookStats::new();
+        let event = Event::Named(NamedEvent::ObjectShow);
+
+        let counters = stats.counters(event);
+        counters.on_received();
+        counters.on_filtered();
+        counters.on_received();
+        counters.on_delivered(Duration::from_micros(1), true);
+
+        let total = stats.snapshot().total();
+        assert_eq!(total.received, 2);
+        assert_eq!(total.filtered, 1);
+        assert_eq!(total.delivered, 1);
+        assert_eq!(total.handler_panics, 1);
+        assert_eq!(total.latency.count(), 1);
+        assert!(total.last_event.is_some());
+
+        let previous = stats.reset();
+        assert_eq!(previous.total(), total);
+        assert!(stats.snapshot().events.is_empty());
+        assert_eq!(stats.snapshot().last_event(), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! Conversion of event times to wall-clock time.
+//!
+//! The `event_time` passed to an [`EventHandler`](crate::EventHandler) is a tick count; milliseconds
+//! since the system started, wrapping every ~49.7 days. An [`EventClock`] anchors tick counts to
+//! wall-clock time, so they can be converted to a [`SystemTime`], [`Instant`] or RFC 3339 string.
+
+use std::{
+    fmt,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(not(windows))]
+use lazy_static::lazy_static;
+
+/// The number of milliseconds after which tick counts wrap.
+const WRAP: i64 = 1 << 32;
+
+/// Anchors tick counts to wall-clock time, resolving them to [`EventTime`]s.
+///
+/// Note: Tick counts are resolved relative to when they're received, so wraparound is handled
+/// regardless of how long the hook has been installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventClock {
+    ticks: u32,
+    system: SystemTime,
+    instant: Instant,
+}
+
+impl EventClock {
+    /// Returns a clock anchored to the current tick count and time.
+    ///
+    /// Note: There's no system tick count outside of windows. Elsewhere, ticks are measured from the
+    /// first time a clock is created.
+    pub fn now() -> Self {
+        Self::new(tick_count(), SystemTime::now(), Instant::now())
+    }
+
+    /// Returns a clock where a given tick count occurred at the given times.
+    pub fn new(ticks: u32, system: SystemTime, instant: Instant) -> Self {
+        Self {
+            ticks,
+            system,
+            instant,
+        }
+    }
+
+    /// The tick count the clock is anchored to.
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// The wall-clock time the clock is anchored to.
+    pub fn system_time(&self) -> SystemTime {
+        self.system
+    }
+
+    /// The monotonic time the clock is anchored to.
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// The tick count at a given time.
+    ///
+    /// Note: This is useful for comparing the current time with event times, for instance to
+    /// [`advance`](crate::merge::Merger::advance) a merge.
+    pub fn ticks_at(&self, instant: Instant) -> u32 {
+        let offset = match instant.checked_duration_since(self.instant) {
+            Some(elapsed) => millis(elapsed),
+            None => -millis(self.instant - instant),
+        };
+
+        self.ticks.wrapping_add(offset as u32)
+    }
+
+    /// Resolves a tick count, for an event that was received now.
+    pub fn resolve(&self, ticks: u32) -> EventTime {
+        self.resolve_at(ticks, Instant::now())
+    }
+
+    /// Resolves a tick count, for an event that was received at a given time.
+    ///
+    /// Note: Of the times the tick count could refer to (one every ~49.7 days), this chooses the
+    /// closest to `received`. Events are delivered long before the tick count could wrap again, so
+    /// this is exact.
+    pub fn resolve_at(&self, ticks: u32, received: Instant) -> EventTime {
+        let expected = match received.checked_duration_since(self.instant) {
+            Some(elapsed) => millis(elapsed),
+            None => -millis(self.instant - received),
+        };
+
+        // the wrapping distance from the expected tick count, interpreted as signed
+        let raw = ticks.wrapping_sub(self.ticks);
+        let distance = raw.wrapping_sub(expected.rem_euclid(WRAP) as u32) as i32;
+
+        EventTime {
+            ticks,
+            offset: expected + i64::from(distance),
+            clock: *self,
+        }
+    }
+}
+
+/// A tick count, resolved by an [`EventClock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventTime {
+    ticks: u32,
+    offset: i64,
+    clock: EventClock,
+}
+
+impl EventTime {
+    /// The tick count, as passed to an [`EventHandler`](crate::EventHandler).
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Milliseconds since the clock was anchored, which are negative for earlier events.
+    ///
+    /// Note: Unlike tick counts, this doesn't wrap.
+    pub fn offset_millis(&self) -> i64 {
+        self.offset
+    }
+
+    /// The wall-clock time of the event.
+    pub fn to_system_time(&self) -> SystemTime {
+        let offset = Duration::from_millis(self.offset.unsigned_abs());
+
+        match self.offset >= 0 {
+            true => self.clock.system.checked_add(offset),
+            false => self.clock.system.checked_sub(offset),
+        }
+        // A failure here indicates a library issue. Please open an issue on GitHub!
+        .expect("Unable to represent event time")
+    }
+
+    /// The monotonic time of the event.
+    ///
+    /// Note: This is suitable for measuring how long ago the event occurred, with [`Instant::elapsed`].
+    pub fn to_instant(&self) -> Instant {
+        let offset = Duration::from_millis(self.offset.unsigned_abs());
+
+        match self.offset >= 0 {
+            true => self.clock.instant.checked_add(offset),
+            false => self.clock.instant.checked_sub(offset),
+        }
+        // A failure here indicates a library issue. Please open an issue on GitHub!
+        .expect("Unable to represent event time")
+    }
+
+    /// The wall-clock time of the event, as an RFC 3339 string in UTC (for instance
+    /// `2024-02-29T13:45:07.250Z`).
+    pub fn to_rfc3339(&self) -> String {
+        format_rfc3339(self.to_system_time())
+    }
+}
+
+impl fmt::Display for EventTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_rfc3339())
+    }
+}
+
+/// Formats a time as an RFC 3339 string in UTC, with millisecond precision.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let millis = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => millis(since),
+        Err(err) => -millis(err.duration()),
+    };
+
+    let (days, millis) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis().try_into().unwrap_or(i64::MAX)
+}
+
+/// Converts days since the unix epoch to a (year, month, day) in the proleptic gregorian calendar.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(windows)]
+fn tick_count() -> u32 {
+    unsafe { windows::Win32::System::SystemInformation::GetTickCount() }
+}
+
+#[cfg(not(windows))]
+fn tick_count() -> u32 {
+    lazy_static! {
+        static ref STARTED: Instant = Instant::now();
+    }
+
+    STARTED.elapsed().as_millis() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    use super::{format_rfc3339, EventClock};
+
+    const WRAP: u64 = 1 << 32;
+
+    #[test]
+    fn resolves_across_wrap_boundary() {
+        let installed = Instant::now();
+        let clock = EventClock::new(u32::MAX - 5, UNIX_EPOCH, installed);
+
+        let time = clock.resolve_at(10, installed + Duration::from_millis(20));
+        assert_eq!(time.offset_millis(), 16);
+        assert_eq!(time.to_instant(), installed + Duration::from_millis(16));
+
+        // an event raised just before the hook was installed
+        let time = clock.resolve_at(u32::MAX - 7, installed + Duration::from_millis(1));
+        assert_eq!(time.offset_millis(), -2);
+        assert_eq!(time.to_instant(), installed - Duration::from_millis(2));
+    }
+
+    #[test]
+    fn resolves_after_several_wraps() {
+        let installed = Instant::now();
+        let clock = EventClock::new(1000, UNIX_EPOCH + Duration::from_secs(60), installed);
+
+        // ~149 days later, just before the tick count passes its value at install again
+        let received = installed + Duration::from_millis(3 * WRAP - 5);
+        let time = clock.resolve_at(990, received);
+
+        assert_eq!(time.offset_millis(), (3 * WRAP - 10) as i64);
+        assert_eq!(
+            time.to_system_time(),
+            UNIX_EPOCH + Duration::from_secs(60) + Duration::from_millis(3 * WRAP - 10)
+        );
+
+        // received late, after the tick count passed its value at install
+        let received = installed + Duration::from_millis(3 * WRAP + 20);
+        assert_eq!(
+            clock.resolve_at(990, received).offset_millis(),
+            (3 * WRAP - 10) as i64
+        );
+        assert_eq!(clock.ticks_at(received), 1020);
+    }
+
+    #[test]
+    fn formats_rfc3339() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + Duration::from_millis(1_709_214_307_250)),
+            "2024-02-29T13:45:07.250Z"
+        );
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH - Duration::from_millis(1)),
+            "1969-12-31T23:59:59.999Z"
+        );
+
+        let clock = EventClock::new(
+            0,
+            UNIX_EPOCH + Duration::from_secs(951_782_400),
+            Instant::now(),
+        );
+        assert_eq!(
+            clock.resolve_at(500, clock.instant()).to_string(),
+            "2000-02-29T00:00:00.500Z"
+        );
+    }
+}